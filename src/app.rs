@@ -1,27 +1,77 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::time::Duration;
 
 use async_channel::Sender;
 use gtk::prelude::*;
 
-use crate::{layout_config::{LayoutConfig, load_layouts, save_overlay, remove_overlay_file}, ui::AppContainer, overlay::show_overlay};
+use crate::{layout_config::{FieldError, LayoutConfig, load_layouts, save_overlay, remove_overlay_file, get_layout_by_name}, ui::{AppContainer, catalog_dialog, layout_editor::{LayoutEditor, EditorMonitor, EditorOverlay}}, overlay::{show_overlay, OverlayCommand, OverlayStatus, placement, edit_mode::{EditContext, EditSettings}, game_window::{track_game_window, FocusedWindow, MANAGER_ROLE}, snapping::Bounds, visibility::VisibilityState}, relay::{Relay, EventSourceStatus, game_state::GameState, synthetic::SyntheticConfig}, app_config::AppConfig};
+
+/// How far a duplicated overlay is moved from the original, towards the inside of the monitor
+const DUPLICATE_OFFSET: i32 = 20;
 
 pub enum AppAction {
     NewOverlay,
     LoadOverlaysList,
     SelectOverlay(LayoutConfig),
-    ToggleOverlay(bool, LayoutConfig),
+    /// A field of the overlay form was edited
+    OverlayFormChanged,
+    /// Put back the loaded values in the overlay form
+    RevertOverlay,
+    /// Enable or disable an overlay, saving the displayed form with it when the last flag is set
+    ToggleOverlay(bool, LayoutConfig, bool),
+    /// Enable or disable every overlay of a folder and its subfolders
+    ToggleFolder(bool, String),
+    /// Move an overlay to a folder of the sidebar, or to the top level
+    MoveToFolder(String, Option<String>),
     SaveOverlay(LayoutConfig),
-    DeleteOverlay(LayoutConfig)
+    DeleteOverlay(LayoutConfig),
+    DuplicateOverlay(LayoutConfig),
+    ReloadOverlay(String),
+    InspectOverlay(String),
+    StartRecording,
+    StopRecording,
+    StartReplay(PathBuf),
+    StartSynthetic(SyntheticConfig),
+    UseLiveEvents,
+    PauseReplay(bool),
+    SetReplaySpeed(f64),
+    SeekReplay(Duration),
+    EventSourceChanged(EventSourceStatus),
+    OverlayStatusChanged(String, OverlayStatus),
+    UpstreamStatusChanged(bool),
+    SetEditMode(Option<EditSettings>),
+    OverlayMoved(String, Bounds),
+    OpenLayoutEditor,
+    /// Overlays moved or resized in the layout editor, with their new absolute bounds
+    LayoutEdited(Vec<(String, Bounds)>),
+    GameWindowChanged(Option<Bounds>),
+    FocusChanged(Option<FocusedWindow>),
+    GameStateChanged(GameState),
+    Quit
 }
 
 pub struct WindowState {
-    pub displayed_overlays: HashMap<String, glib::Sender<bool>>,
+    pub displayed_overlays: HashMap<String, glib::Sender<OverlayCommand>>,
+    pub overlay_statuses: HashMap<String, OverlayStatus>,
+    /// The overlays connected to the upstream server through the relay
+    pub relayed_overlays: HashSet<String>,
+    pub is_upstream_connected: bool,
+    pub event_source: EventSourceStatus,
+    /// Set while the overlays are moved around from the layout toolbar
+    pub edit_settings: Option<EditSettings>,
+    /// The client area of the game window, when it is found
+    pub game_area: Option<Bounds>,
+    /// The focused window and the state of the game the overlays visibility depends on
+    pub visibility_state: VisibilityState,
     pub event_sender: Sender<AppAction>
 }
 
 pub struct App {
     window: gtk::Window,
     app_container: AppContainer,
+    layout_editor: LayoutEditor,
+    relay: Option<Relay>,
     state: WindowState
 }
 
@@ -29,23 +79,47 @@ impl App {
     pub fn new(sender: Sender<AppAction>) -> Self {
         let window = gtk::Window::new(gtk::WindowType::Toplevel);
         window.set_size_request(1000, 700);
-        window.connect_delete_event(|_, _| {
-            gtk::main_quit();
-            Inhibit(false)
+        window.set_role(MANAGER_ROLE);
+        // The unsaved changes of the form are checked before quitting
+        let quit_sender = sender.clone();
+        window.connect_delete_event(move |_, _| {
+            let _ = glib::MainContext::default().block_on(quit_sender.send(AppAction::Quit));
+            Inhibit(true)
         });
 
         let state = WindowState {
             displayed_overlays: HashMap::default(),
+            overlay_statuses: HashMap::default(),
+            relayed_overlays: HashSet::default(),
+            is_upstream_connected: false,
+            event_source: EventSourceStatus::Live,
+            edit_settings: None,
+            game_area: None,
+            visibility_state: VisibilityState::default(),
             event_sender: sender.clone()
         };
 
-        let app_container = AppContainer::new(sender);
+        let app_container = AppContainer::new(sender.clone());
+        let layout_editor = LayoutEditor::new(sender.clone());
 
         window.add(&app_container.container);
 
+        let app_config = AppConfig::default();
+        track_game_window(app_config.game_window_rule().clone(), sender.clone());
+
+        let relay = match Relay::start(&app_config, sender) {
+            Ok(relay) => Some(relay),
+            Err(error) => {
+                error!("Could not start the relay, the overlays will connect directly. Error {:?}", error);
+                None
+            }
+        };
+
         let mut app = Self { 
             window,
             app_container,
+            layout_editor,
+            relay,
             state
         };
 
@@ -82,6 +156,8 @@ impl App {
 
         let overlays = load_layouts();
         self.app_container.sidebar.display_overlays_list(overlays);
+        self.refresh_overlays_status();
+        self.refresh_layout_editor();
     }
 
     pub fn display_overlay_details(&mut self, overlay: LayoutConfig) {
@@ -89,11 +165,98 @@ impl App {
 
         self.app_container.set_details_visible(true);
         self.app_container.overlay_details.set_current_overlay(overlay);
+        self.refresh_preview();
+    }
+
+    pub fn select_overlay(&mut self, overlay: LayoutConfig) {
+        if self.confirm_discard() {
+            self.display_overlay_details(overlay);
+        }
+    }
+
+    pub fn refresh_form_state(&self) {
+        self.app_container.overlay_details.refresh_form_state();
+        self.refresh_preview();
+    }
+
+    /// The preview receives the events of the relay, like the opened overlays
+    fn refresh_preview(&self) {
+        self.app_container.overlay_details.refresh_preview(|url| match &self.relay {
+            Some(relay) => relay.overlay_url(url),
+            None => url.to_string()
+        });
+    }
+
+    pub fn revert_overlay(&mut self) {
+        let overlay = self.app_container.overlay_details.current_overlay();
+        self.display_overlay_details(overlay);
+    }
+
+    /// Show the new saved state of the displayed overlay, without losing the edits of the form
+    fn refresh_overlay_details(&mut self, overlay: LayoutConfig) {
+        if self.app_container.overlay_details.is_modified() {
+            self.app_container.overlay_details.set_saved_overlay(overlay);
+        } else {
+            self.display_overlay_details(overlay);
+        }
     }
 
-    pub fn toggle_overlay(&mut self, new_state: bool, overlay: LayoutConfig) {
+    /// Ask what to do with the unsaved changes of the form, returns whether the form can be left
+    fn confirm_discard(&mut self) -> bool {
+        if !self.app_container.overlay_details.is_modified() {
+            return true;
+        }
+
+        let overlay_name = self.app_container.overlay_details.current_overlay_name();
+        let message = if overlay_name.is_empty() {
+            "The new overlay has unsaved changes.".to_string()
+        } else {
+            format!("The overlay {} has unsaved changes.", overlay_name)
+        };
+        let dialog = gtk::MessageDialog::builder()
+            .transient_for(&self.window)
+            .modal(true)
+            .title("Unsaved changes")
+            .message_type(gtk::MessageType::Question)
+            .text(&message)
+            .secondary_text("Save them before leaving the form ?")
+            .build();
+        dialog.add_buttons(&[
+            ("Cancel", gtk::ResponseType::Cancel),
+            ("Discard", gtk::ResponseType::Reject),
+            ("Save", gtk::ResponseType::Accept)
+        ]);
+        dialog.set_default_response(gtk::ResponseType::Accept);
+        let response = dialog.run();
+        dialog.close();
+
+        match response {
+            gtk::ResponseType::Accept => {
+                let mut overlay = self.app_container.overlay_details.current_overlay();
+                self.save_overlay(&mut overlay);
+                // The form stays when the save failed
+                !self.app_container.overlay_details.is_modified()
+            },
+            gtk::ResponseType::Reject => true,
+            _ => false
+        }
+    }
+
+    pub fn quit(&mut self) {
+        if self.confirm_discard() {
+            gtk::main_quit();
+        }
+    }
+
+    pub fn toggle_overlay(&mut self, new_state: bool, overlay: LayoutConfig, save_form: bool) {
         info!("Toggle overlay to {:?} {}", overlay.name(), new_state);
 
+        let errors = overlay.validate();
+        if new_state && !errors.is_empty() {
+            self.show_dialog("The overlay can not be enabled", &errors_message(&errors));
+            return;
+        }
+
         if new_state {
             self.open_overlay(&overlay);
         } else {
@@ -102,30 +265,103 @@ impl App {
 
         let mut new_overlay = overlay.clone();
         new_overlay.set_active(new_state);
-        self.save_overlay(&mut new_overlay)
+
+        // The header switch saves the displayed form with the new state, the sidebar or an invalid form only the state
+        let overlay_details = &self.app_container.overlay_details;
+        let is_displayed = overlay.name() == overlay_details.current_overlay_name();
+        if save_form && is_displayed && overlay_details.form_errors().is_empty() {
+            self.save_overlay(&mut new_overlay);
+        } else if let Err(error) = save_overlay(new_overlay.clone()) {
+            error!("Could not save the overlay {} ! Error {:?}", overlay.name(), error);
+            self.show_dialog("Error while saving the overlay", error.to_string().as_str());
+        } else if is_displayed {
+            self.app_container.overlay_details.set_saved_overlay(new_overlay);
+        }
+        self.refresh_layout_editor();
+    }
+
+    pub fn reload_overlay(&self, overlay_name: String) {
+        if let Some(sender) = self.state.displayed_overlays.get(&overlay_name) {
+            let _ = sender.send(OverlayCommand::Reload);
+        }
+    }
+
+    pub fn inspect_overlay(&self, overlay_name: String) {
+        if let Some(sender) = self.state.displayed_overlays.get(&overlay_name) {
+            let _ = sender.send(OverlayCommand::ShowInspector);
+        }
+    }
+
+    pub fn toggle_folder(&mut self, new_state: bool, folder: String) {
+        info!("Toggle the overlays of the folder {} to {}", folder, new_state);
+
+        let current_overlay_name = self.app_container.overlay_details.current_overlay_name();
+        for mut overlay in load_layouts() {
+            if !overlay.is_in_folder(&folder) || overlay.is_active() == new_state {
+                continue;
+            }
+            // The invalid overlays stay disabled until they are fixed in the form
+            if new_state && !overlay.validate().is_empty() {
+                continue;
+            }
+
+            if new_state {
+                self.open_overlay(&overlay);
+            } else {
+                self.close_overlay(&overlay);
+            }
+
+            // The form is not involved, unlike a toggle of the overlay itself
+            overlay.set_active(new_state);
+            if let Err(error) = save_overlay(overlay.clone()) {
+                error!("Could not save the overlay {} ! Error {:?}", overlay.name(), error);
+            }
+            if overlay.name() == current_overlay_name {
+                self.refresh_overlay_details(overlay);
+            }
+        }
+
+        self.load_overlays_list();
+    }
+
+    pub fn move_to_folder(&mut self, overlay_name: String, folder: Option<String>) {
+        let Ok(mut overlay) = get_layout_by_name(&overlay_name) else {
+            return;
+        };
+        if overlay.folder() == folder {
+            return;
+        }
+
+        info!("Moving the overlay {} to the folder {:?}", overlay_name, folder);
+        overlay.set_folder(folder);
+        if let Err(error) = save_overlay(overlay.clone()) {
+            self.show_dialog("Error while moving the overlay", error.to_string().as_str());
+            return;
+        }
+
+        // The displayed form keeps the overlay it was opened with
+        if overlay_name == self.app_container.overlay_details.current_overlay_name() {
+            self.refresh_overlay_details(overlay);
+        }
+        self.load_overlays_list();
     }
 
     pub fn save_overlay(&mut self, overlay: &mut LayoutConfig) {
-        let overlay_details = &self.app_container.overlay_details;
-        
+        let errors = self.app_container.overlay_details.form_errors();
+        if !errors.is_empty() {
+            self.app_container.overlay_details.refresh_form_state();
+            self.show_dialog("The overlay can not be saved", &errors_message(&errors));
+            return;
+        }
+
         if overlay.is_active() {
             self.close_overlay(overlay);
         }
         
         let old_overlay= overlay.clone();
-        let need_delete = overlay_details.name_entry.text() != old_overlay.name();
-        if need_delete {
-            overlay.set_name(overlay_details.name_entry.text());
-        }
-
-        overlay.set_url(overlay_details.url_entry.text());
-        overlay.set_x(overlay_details.x_pos_spin.value_as_int());
-        overlay.set_y(overlay_details.y_pos_spin.value_as_int());
-        overlay.set_width(overlay_details.width_spin.value_as_int());
-        overlay.set_height(overlay_details.height_spin.value_as_int());
-        overlay.set_is_clickthrough(overlay_details.clickthrough_check.is_active());
-        overlay.set_is_decorated(overlay_details.movable_check.is_active());
-        
+        *overlay = overlay.with_form(&self.app_container.overlay_details.form_values());
+        let need_delete = overlay.name() != old_overlay.name();
+
         match save_overlay(overlay.clone()) {
             Ok(_) => {
                 // Remove the old overlay if the name changed
@@ -149,7 +385,7 @@ impl App {
 
     }
 
-    pub fn delete_overlay(&self, overlay: &LayoutConfig) {
+    pub fn delete_overlay(&mut self, overlay: &LayoutConfig) {
         if let Err(error) = remove_overlay_file(overlay.get_file_name()) {
             self.show_dialog("Error while deleting the old file", error.to_string().as_str());
             return;
@@ -158,37 +394,399 @@ impl App {
         self.close_overlay(overlay);
 
         // Reset the inputs to default
-        let overlay_details = &self.app_container.overlay_details;
-        overlay_details.url_entry.set_text("");
-        overlay_details.x_pos_spin.set_value(0.0);
-        overlay_details.y_pos_spin.set_value(0.0);
-        overlay_details.width_spin.set_value(0.0);
-        overlay_details.height_spin.set_value(0.0);
-        overlay_details.clickthrough_check.set_active(false);
-        overlay_details.movable_check.set_active(false);
+        self.app_container.overlay_details.set_current_overlay(LayoutConfig::default());
 
         // Hide the container
         self.app_container.set_details_visible(false);
     }
 
+    /// Save a disabled copy of the overlay under a new name, next to the original
+    pub fn duplicate_overlay(&mut self, overlay: LayoutConfig) {
+        if !self.confirm_discard() {
+            return;
+        }
+
+        let file_names: HashSet<String> = load_layouts().iter().map(LayoutConfig::get_file_name).collect();
+
+        let mut copy = overlay.clone();
+        copy.set_name(format!("{} (copy)", overlay.name()));
+        let mut copy_number = 2;
+        while file_names.contains(&copy.get_file_name()) {
+            copy.set_name(format!("{} (copy {})", overlay.name(), copy_number));
+            copy_number += 1;
+        }
+        copy.set_x(overlay.x() + DUPLICATE_OFFSET);
+        copy.set_y(overlay.y() + DUPLICATE_OFFSET);
+        copy.set_active(false);
+
+        info!("Duplicating the overlay {} as {}", overlay.name(), copy.name());
+        if let Err(error) = save_overlay(copy.clone()) {
+            error!("Could not save the copy of the overlay ! Error {:?}", error);
+            self.show_dialog("Error while duplicating the overlay", error.to_string().as_str());
+            return;
+        }
+
+        self.load_overlays_list();
+        self.app_container.sidebar.select(&copy.name());
+        self.display_overlay_details(copy);
+    }
+
     pub fn new_overlay(&mut self) {
-        self.display_overlay_details(LayoutConfig::default());
+        if !self.confirm_discard() {
+            return;
+        }
+
+        let app_config = AppConfig::default();
+        let Some(mut overlay) = catalog_dialog::pick_new_overlay(&self.window, app_config.relay_upstream_url()) else {
+            return;
+        };
+
+        // The catalog overlays can be added several times
+        if !overlay.name().is_empty() {
+            let file_names: HashSet<String> = load_layouts().iter().map(LayoutConfig::get_file_name).collect();
+            let base_name = overlay.name();
+            let mut copy_number = 2;
+            while file_names.contains(&overlay.get_file_name()) {
+                overlay.set_name(format!("{} {}", base_name, copy_number));
+                copy_number += 1;
+            }
+        }
+
+        self.app_container.set_details_visible(true);
+        self.app_container.overlay_details.set_new_overlay(overlay);
+        self.refresh_preview();
+    }
+
+    pub fn start_recording(&self) {
+        let Some(relay) = &self.relay else {
+            self.app_container.replay_bar.set_recording(false);
+            self.show_dialog("Could not start the recording", "The relay is not running");
+            return;
+        };
+
+        if let Err(error) = relay.start_recording() {
+            error!("Could not start the recording ! Error {:?}", error);
+            self.app_container.replay_bar.set_recording(false);
+            self.show_dialog("Could not start the recording", error.to_string().as_str());
+        }
+    }
+
+    pub fn stop_recording(&self) {
+        match self.relay.as_ref().and_then(Relay::stop_recording) {
+            Some(Ok(path)) => info!("Recording saved to {:?}", path),
+            Some(Err(error)) => {
+                error!("Could not save the recording ! Error {:?}", error);
+                self.show_dialog("Error while saving the recording", error.to_string().as_str());
+            },
+            None => {}
+        }
+    }
+
+    pub fn start_replay(&self, path: PathBuf) {
+        let Some(relay) = &self.relay else {
+            self.show_dialog("Could not replay the recording", "The relay is not running");
+            return;
+        };
+
+        if let Err(error) = relay.start_replay(&path) {
+            error!("Could not replay the recording {:?} ! Error {:?}", path, error);
+            self.show_dialog("Could not replay the recording", error.to_string().as_str());
+        }
+    }
+
+    pub fn start_synthetic(&self, config: SyntheticConfig) {
+        match &self.relay {
+            Some(relay) => relay.start_synthetic(config),
+            None => self.show_dialog("Could not generate the synthetic events", "The relay is not running")
+        }
+    }
+
+    pub fn use_live_events(&self) {
+        if let Some(relay) = &self.relay {
+            relay.use_live_events();
+        }
+    }
+
+    pub fn pause_replay(&self, is_paused: bool) {
+        if let Some(relay) = &self.relay {
+            relay.set_replay_paused(is_paused);
+        }
+    }
+
+    pub fn set_replay_speed(&self, speed: f64) {
+        if let Some(relay) = &self.relay {
+            relay.set_replay_speed(speed);
+        }
+    }
+
+    pub fn seek_replay(&self, position: Duration) {
+        if let Some(relay) = &self.relay {
+            relay.seek_replay(position);
+        }
+    }
+
+    pub fn display_event_source(&mut self, status: EventSourceStatus) {
+        self.app_container.replay_bar.set_source_status(status);
+
+        let was_live = self.state.event_source == EventSourceStatus::Live;
+        self.state.event_source = status;
+        if was_live != (status == EventSourceStatus::Live) {
+            self.refresh_overlays_status();
+        }
+    }
+
+    pub fn set_upstream_status(&mut self, is_connected: bool) {
+        self.state.is_upstream_connected = is_connected;
+        self.refresh_overlays_status();
+    }
+
+    pub fn set_overlay_status(&mut self, overlay_name: String, status: OverlayStatus) {
+        // Late reports of an overlay that has been closed since
+        if !self.state.displayed_overlays.contains_key(&overlay_name) {
+            return;
+        }
+
+        if let OverlayStatus::Failed(reason) = &status {
+            warn!("The overlay {} is unhealthy: {}", overlay_name, reason);
+        }
+        self.state.overlay_statuses.insert(overlay_name.clone(), status);
+        self.refresh_overlay_status(&overlay_name);
+    }
+
+    fn displayed_status(&self, overlay_name: &str) -> Option<OverlayStatus> {
+        let status = self.state.overlay_statuses.get(overlay_name)?;
+        let is_starved = !self.state.is_upstream_connected
+            && self.state.event_source == EventSourceStatus::Live
+            && self.state.relayed_overlays.contains(overlay_name);
+
+        match status {
+            OverlayStatus::Running if is_starved => Some(OverlayStatus::Disconnected),
+            status => Some(status.clone())
+        }
+    }
+
+    fn refresh_overlay_status(&self, overlay_name: &str) {
+        let status = self.displayed_status(overlay_name);
+        self.app_container.sidebar.set_overlay_status(overlay_name, status.as_ref());
+    }
+
+    fn refresh_overlays_status(&self) {
+        for overlay_name in self.state.overlay_statuses.keys() {
+            self.refresh_overlay_status(overlay_name);
+        }
+    }
+
+    pub fn set_edit_mode(&mut self, settings: Option<EditSettings>) {
+        self.state.edit_settings = settings;
+        self.send_edit_contexts();
+    }
+
+    pub fn move_overlay(&mut self, overlay_name: String, bounds: Bounds) {
+        let mut overlay = match get_layout_by_name(&overlay_name) {
+            Ok(overlay) => overlay,
+            Err(error) => {
+                error!("Could not save the position of the overlay {} ! Error {:?}", overlay_name, error);
+                return;
+            }
+        };
+
+        if let Some(display) = gdk::Display::default() {
+            let game_area = self.state.game_area.map(Bounds::to_rectangle);
+            placement::move_config_to(&mut overlay, &bounds.to_rectangle(), &display, game_area.as_ref());
+        }
+
+        if let Err(error) = save_overlay(overlay.clone()) {
+            error!("Could not save the position of the overlay {} ! Error {:?}", overlay_name, error);
+            self.show_dialog("Error while saving the overlay", error.to_string().as_str());
+            return;
+        }
+
+        if self.app_container.overlay_details.current_overlay_name() == overlay_name {
+            self.refresh_overlay_details(overlay);
+        }
+        self.send_edit_contexts();
+        self.refresh_layout_editor();
     }
 
-    pub fn close_overlay(&self, overlay: &LayoutConfig) {
-        if let Some(sender) = self.state.displayed_overlays.get(&overlay.name()) {
-            sender.send(true).unwrap();
+    pub fn open_layout_editor(&self) {
+        self.layout_editor.show();
+        self.refresh_layout_editor();
+    }
+
+    /// Save the overlays moved or resized in the layout editor, the opened ones are moved right away
+    pub fn edit_layout(&mut self, edits: Vec<(String, Bounds)>) {
+        let Some(display) = gdk::Display::default() else {
+            return;
+        };
+        let game_area = self.state.game_area.map(Bounds::to_rectangle);
+
+        for (overlay_name, bounds) in edits {
+            let Ok(mut overlay) = get_layout_by_name(&overlay_name) else {
+                continue;
+            };
+            placement::resize_config_to(&mut overlay, &bounds.to_rectangle(), &display, game_area.as_ref());
+
+            if let Err(error) = save_overlay(overlay.clone()) {
+                error!("Could not save the position of the overlay {} ! Error {:?}", overlay_name, error);
+                self.show_dialog("Error while saving the overlay", error.to_string().as_str());
+                break;
+            }
+
+            if let Some(sender) = self.state.displayed_overlays.get(&overlay_name) {
+                // The opened overlay keeps the url of the relay
+                let mut displayed_overlay = overlay.clone();
+                if let Some(relay) = &self.relay {
+                    displayed_overlay.set_url(relay.overlay_url(&overlay.url()));
+                }
+                let _ = sender.send(OverlayCommand::SetPlacement(displayed_overlay));
+            }
+            if self.app_container.overlay_details.current_overlay_name() == overlay_name {
+                self.refresh_overlay_details(overlay);
+            }
+        }
+
+        if self.state.edit_settings.is_some() {
+            self.send_edit_contexts();
+        }
+        self.refresh_layout_editor();
+    }
+
+    /// Show the saved geometry of every overlay in the layout editor, while it is opened
+    fn refresh_layout_editor(&self) {
+        if !self.layout_editor.is_visible() {
+            return;
+        }
+        let Some(display) = gdk::Display::default() else {
+            return;
+        };
+
+        let game_area = self.state.game_area.map(Bounds::to_rectangle);
+        let monitors = placement::monitors(&display)
+            .into_iter()
+            .map(|monitor| EditorMonitor {
+                name: monitor.identifier,
+                bounds: Bounds::from_rectangle(&monitor.geometry)
+            })
+            .collect();
+        let overlays = load_layouts()
+            .iter()
+            .map(|overlay| EditorOverlay {
+                name: overlay.name(),
+                bounds: Bounds::from_rectangle(&placement::overlay_geometry(overlay, &display, game_area.as_ref())),
+                is_active: overlay.is_active()
+            })
+            .collect();
+        self.layout_editor.set_layout(monitors, overlays);
+    }
+
+    /// Tell every displayed overlay whether it is edited, and where the other ones are to snap to them
+    fn send_edit_contexts(&self) {
+        let Some(settings) = self.state.edit_settings else {
+            for sender in self.state.displayed_overlays.values() {
+                let _ = sender.send(OverlayCommand::SetEditMode(None));
+            }
+            return;
+        };
+
+        let Some(display) = gdk::Display::default() else {
+            return;
+        };
+        let game_area = self.state.game_area.map(Bounds::to_rectangle);
+        let overlays_bounds: Vec<(String, Bounds)> = load_layouts()
+            .iter()
+            .filter(|overlay| self.state.displayed_overlays.contains_key(&overlay.name()))
+            .map(|overlay| {
+                let geometry = placement::overlay_geometry(overlay, &display, game_area.as_ref());
+                (overlay.name(), Bounds::from_rectangle(&geometry))
+            })
+            .collect();
+
+        for (overlay_name, sender) in self.state.displayed_overlays.iter() {
+            let context = EditContext {
+                grid: settings.grid,
+                other_overlays: overlays_bounds
+                    .iter()
+                    .filter(|(name, _)| name != overlay_name)
+                    .map(|(_, bounds)| *bounds)
+                    .collect()
+            };
+            let _ = sender.send(OverlayCommand::SetEditMode(Some(context)));
+        }
+    }
+
+    pub fn set_game_area(&mut self, game_area: Option<Bounds>) {
+        self.state.game_area = game_area;
+        for sender in self.state.displayed_overlays.values() {
+            let _ = sender.send(OverlayCommand::SetGameArea(game_area));
+        }
+        if self.state.edit_settings.is_some() {
+            self.send_edit_contexts();
+        }
+        self.refresh_layout_editor();
+    }
+
+    pub fn set_focused_window(&mut self, focused_window: Option<FocusedWindow>) {
+        self.state.visibility_state.focus = focused_window;
+        for sender in self.state.displayed_overlays.values() {
+            let _ = sender.send(OverlayCommand::SetFocus(focused_window));
+        }
+    }
+
+    pub fn set_game_state(&mut self, game_state: GameState) {
+        debug!("Game state changed to {:?}", game_state);
+
+        self.state.visibility_state.game_state = game_state.clone();
+        for sender in self.state.displayed_overlays.values() {
+            let _ = sender.send(OverlayCommand::SetGameState(game_state.clone()));
+        }
+    }
+
+    pub fn close_overlay(&mut self, overlay: &LayoutConfig) {
+        if let Some(sender) = self.state.displayed_overlays.remove(&overlay.name()) {
+            sender.send(OverlayCommand::Close).unwrap();
+        }
+        self.state.overlay_statuses.remove(&overlay.name());
+        self.state.relayed_overlays.remove(&overlay.name());
+        self.refresh_overlay_status(&overlay.name());
+        if self.state.edit_settings.is_some() {
+            self.send_edit_contexts();
         }
     }
 
     fn open_overlay(&mut self, overlay: &LayoutConfig) {
+        // Checked again as the files can be edited by hand
+        let errors = overlay.validate();
+        if !errors.is_empty() {
+            let reason = errors_message(&errors);
+            warn!("The overlay {} is not opened, its settings are invalid: {}", overlay.name(), reason);
+            // Shown in the sidebar until the overlay is fixed or disabled
+            self.state.overlay_statuses.insert(overlay.name(), OverlayStatus::Failed(reason));
+            self.refresh_overlay_status(&overlay.name());
+            return;
+        }
+
         let (win_sender, win_receiver) = glib::MainContext::channel(glib::Priority::default());
-        let overlay_cloned = overlay.clone();
+        let event_sender = self.state.event_sender.clone();
+        let game_area = self.state.game_area;
+        let visibility_state = self.state.visibility_state.clone();
+        let mut overlay_cloned = overlay.clone();
+        if let Some(relay) = &self.relay {
+            overlay_cloned.set_url(relay.overlay_url(&overlay.url()));
+        }
+        if overlay_cloned.url() != overlay.url() {
+            self.state.relayed_overlays.insert(overlay.name());
+        }
 
         self.state.displayed_overlays.insert(overlay_cloned.name(), win_sender);
+        self.state.overlay_statuses.insert(overlay.name(), OverlayStatus::Loading);
+        self.refresh_overlay_status(&overlay.name());
         glib::MainContext::default().invoke(move || {
-            show_overlay(&overlay_cloned.clone(), win_receiver);
+            show_overlay(&overlay_cloned.clone(), game_area, visibility_state, win_receiver, event_sender);
         });
+
+        if self.state.edit_settings.is_some() {
+            self.send_edit_contexts();
+        }
     }
 
     fn show_dialog(&self, title: &str, message: &str) {
@@ -200,3 +798,7 @@ impl App {
         dialog_window.show();
     }
 }
+
+fn errors_message(errors: &[FieldError]) -> String {
+    errors.iter().map(FieldError::to_string).collect::<Vec<String>>().join("\n")
+}
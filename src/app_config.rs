@@ -1,18 +1,98 @@
 use std::path::Path;
 
+use crate::overlay::game_window::GameWindowRule;
+use crate::overlay::interaction::parse_modifiers;
+
 pub struct AppConfig {
-    layouts_config_path: String
+    layouts_config_path: String,
+    recordings_path: String,
+    folders_path: String,
+    relay_upstream_url: String,
+    relay_port: u32,
+    game_window_rule: GameWindowRule,
+    use_layer_shell: bool,
+    interaction_modifiers: Option<gdk::ModifierType>
 }
 
 impl Default for AppConfig {
     fn default() -> Self {
         let home_dir = std::env::var("HOME").unwrap_or("./".to_string());
-        AppConfig { layouts_config_path: format!("{}/.config/xiv-overlay/", home_dir) }
+        let relay_upstream_url = std::env::var("XIV_OVERLAY_UPSTREAM")
+            .unwrap_or("ws://127.0.0.1:10501/ws".to_string());
+        let relay_port = std::env::var("XIV_OVERLAY_RELAY_PORT")
+            .ok()
+            .and_then(|port| port.parse().ok())
+            .unwrap_or(10511);
+        let game_window_rule = std::env::var("XIV_OVERLAY_GAME_WINDOW")
+            .map(|rule| GameWindowRule::parse(&rule))
+            .unwrap_or_default();
+        let use_layer_shell = std::env::var("XIV_OVERLAY_LAYER_SHELL")
+            .map(|value| !matches!(value.as_str(), "0" | "false" | "no"))
+            .unwrap_or(true);
+        let interaction_modifiers = parse_modifiers(
+            &std::env::var("XIV_OVERLAY_INTERACT_MODIFIERS").unwrap_or("ctrl+alt".to_string())
+        );
+
+        AppConfig {
+            layouts_config_path: format!("{}/.config/xiv-overlay/", home_dir),
+            recordings_path: format!("{}/.local/share/xiv-overlay/recordings/", home_dir),
+            // Next to the overlays directory, which only holds overlays
+            folders_path: format!("{}/.config/xiv-overlay-folders.yaml", home_dir),
+            relay_upstream_url,
+            relay_port,
+            game_window_rule,
+            use_layer_shell,
+            interaction_modifiers
+        }
     }
 }
 
 impl AppConfig {
+    /// Relay to a stand-in upstream server, the other settings are the default ones
+    #[cfg(test)]
+    pub fn with_relay(relay_upstream_url: &str, relay_port: u32) -> Self {
+        AppConfig {
+            relay_upstream_url: relay_upstream_url.to_string(),
+            relay_port,
+            ..AppConfig::default()
+        }
+    }
+
     pub fn layouts_config_path(&self) -> &Path {
         Path::new(&self.layouts_config_path)
     }
-}
\ No newline at end of file
+
+    pub fn recordings_path(&self) -> &Path {
+        Path::new(&self.recordings_path)
+    }
+
+    /// The folders created in the sidebar, which may have no overlay
+    pub fn folders_path(&self) -> &Path {
+        Path::new(&self.folders_path)
+    }
+
+    /// The OverlayPlugin server (IINACT) the relay connects to
+    pub fn relay_upstream_url(&self) -> &str {
+        &self.relay_upstream_url
+    }
+
+    /// The local port the relay serves the overlays on
+    pub fn relay_port(&self) -> u32 {
+        self.relay_port
+    }
+
+    /// How the game window the overlays can follow is recognized
+    pub fn game_window_rule(&self) -> &GameWindowRule {
+        &self.game_window_rule
+    }
+
+    /// Whether the overlays are layer surfaces on Wayland compositors supporting it
+    pub fn use_layer_shell(&self) -> bool {
+        self.use_layer_shell
+    }
+
+    /// The modifiers to hold to interact with the clickthrough overlay under the pointer
+    pub fn interaction_modifiers(&self) -> Option<gdk::ModifierType> {
+        self.interaction_modifiers
+    }
+}
@@ -0,0 +1,92 @@
+use serde::Deserialize;
+
+use crate::{layout_config::LayoutConfig, relay::OVERLAY_WS_PARAM};
+
+const CATALOG: &str = include_str!("./overlays.yaml");
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct QueryParameter {
+    pub name: String,
+    pub value: String
+}
+
+/// A well known overlay, with the settings it is usually displayed with
+#[derive(Debug, Deserialize, Clone)]
+pub struct CatalogEntry {
+    pub name: String,
+    pub description: String,
+    /// Without the query, the websocket parameter is added when the overlay is created
+    pub url: String,
+    #[serde(default)]
+    pub query: Vec<QueryParameter>,
+    /// Recommended size, in pixels
+    pub width: i32,
+    pub height: i32,
+    /// The overlays only displaying information do not need the mouse
+    #[serde(default)]
+    pub clickthrough: bool
+}
+
+impl CatalogEntry {
+    /// The url of the overlay connected to the OverlayPlugin server
+    pub fn overlay_url(&self, websocket_url: &str) -> String {
+        let escape = |value: &str| glib::Uri::escape_string(value, None, true);
+        let query = std::iter::once(format!("{}={}", OVERLAY_WS_PARAM, escape(websocket_url)))
+            .chain(self.query.iter().map(|parameter| format!("{}={}", escape(&parameter.name), escape(&parameter.value))))
+            .collect::<Vec<String>>()
+            .join("&");
+        format!("{}?{}", self.url, query)
+    }
+
+    /// A new overlay pre-filled from the entry, disabled until it is saved and enabled
+    pub fn to_layout_config(&self, websocket_url: &str) -> LayoutConfig {
+        let mut overlay = LayoutConfig::default();
+        overlay.set_name(self.name.clone());
+        overlay.set_url(self.overlay_url(websocket_url));
+        overlay.set_width(self.width);
+        overlay.set_height(self.height);
+        overlay.set_is_clickthrough(self.clickthrough);
+        overlay
+    }
+}
+
+/// The overlays shipped with the application, in the order they are offered
+pub fn catalog_entries() -> Vec<CatalogEntry> {
+    serde_yaml::from_str(CATALOG).expect("Could not parse the overlays catalog")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WEBSOCKET_URL: &str = "ws://127.0.0.1:10501/ws";
+
+    #[test]
+    fn every_entry_makes_a_valid_overlay() {
+        let entries = catalog_entries();
+        assert!(!entries.is_empty());
+
+        for entry in entries {
+            let errors = entry.to_layout_config(WEBSOCKET_URL).validate();
+            assert!(errors.is_empty(), "The overlay {} is invalid: {:?}", entry.name, errors);
+        }
+    }
+
+    #[test]
+    fn escapes_the_query_of_the_overlay_url() {
+        let entry = CatalogEntry {
+            name: "Meter".to_string(),
+            description: String::new(),
+            url: "https://meter.example/".to_string(),
+            query: vec![QueryParameter { name: "theme".to_string(), value: "dark & wide".to_string() }],
+            width: 400,
+            height: 200,
+            clickthrough: false
+        };
+
+        assert_eq!(
+            entry.overlay_url(WEBSOCKET_URL),
+            "https://meter.example/?OVERLAY_WS=ws%3A%2F%2F127.0.0.1%3A10501%2Fws&theme=dark%20%26%20wide"
+        );
+    }
+}
@@ -1,17 +1,269 @@
+use std::collections::BTreeSet;
+use std::time::{Duration, SystemTime};
+
 use serde::{Serialize, Deserialize};
 
 use crate::{app_config::AppConfig, errors::OverlayConfigParseError};
 
+/// The point of the monitor the overlay is attached to, x and y are offsets towards its inside
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "kebab-case")]
+pub enum Anchor {
+    #[default]
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight
+}
+
+impl Anchor {
+    pub const ALL: [Anchor; 9] = [
+        Anchor::TopLeft,
+        Anchor::Top,
+        Anchor::TopRight,
+        Anchor::Left,
+        Anchor::Center,
+        Anchor::Right,
+        Anchor::BottomLeft,
+        Anchor::Bottom,
+        Anchor::BottomRight,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Anchor::TopLeft => "Top left",
+            Anchor::Top => "Top",
+            Anchor::TopRight => "Top right",
+            Anchor::Left => "Left",
+            Anchor::Center => "Center",
+            Anchor::Right => "Right",
+            Anchor::BottomLeft => "Bottom left",
+            Anchor::Bottom => "Bottom",
+            Anchor::BottomRight => "Bottom right",
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "kebab-case")]
+pub enum SizeUnit {
+    #[default]
+    Pixels,
+    /// Percentage of the monitor, or game window, size
+    Percent
+}
+
+/// What still catches the mouse on a clickthrough overlay
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "kebab-case")]
+pub enum ClickthroughMode {
+    /// Only the regions declared by the page
+    #[default]
+    DeclaredRegions,
+    /// The parts of the page which are not fully transparent, read from its rendering
+    OpaquePixels
+}
+
+impl ClickthroughMode {
+    pub const ALL: [ClickthroughMode; 2] = [
+        ClickthroughMode::DeclaredRegions,
+        ClickthroughMode::OpaquePixels,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ClickthroughMode::DeclaredRegions => "Regions declared by the page",
+            ClickthroughMode::OpaquePixels => "Visible parts of the page",
+        }
+    }
+}
+
+/// How often the rendering of an overlay is read in the opaque pixels mode
+pub const DEFAULT_ALPHA_SAMPLING_INTERVAL: Duration = Duration::from_millis(1000);
+
+/// When the overlay is shown, depending on the focused window
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "kebab-case")]
+pub enum Visibility {
+    #[default]
+    Always,
+    GameFocused,
+    GameOrManagerFocused
+}
+
+impl Visibility {
+    pub const ALL: [Visibility; 3] = [
+        Visibility::Always,
+        Visibility::GameFocused,
+        Visibility::GameOrManagerFocused,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Visibility::Always => "Always",
+            Visibility::GameFocused => "While the game is focused",
+            Visibility::GameOrManagerFocused => "While the game or the manager is focused",
+        }
+    }
+}
+
+/// What the overlay does once no data or pointer activity happened for a while
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "kebab-case")]
+pub enum IdleBehaviour {
+    #[default]
+    Stay,
+    Fade,
+    Hide
+}
+
+impl IdleBehaviour {
+    pub const ALL: [IdleBehaviour; 3] = [
+        IdleBehaviour::Stay,
+        IdleBehaviour::Fade,
+        IdleBehaviour::Hide,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            IdleBehaviour::Stay => "Stay visible",
+            IdleBehaviour::Fade => "Fade out",
+            IdleBehaviour::Hide => "Hide",
+        }
+    }
+}
+
+pub const DEFAULT_IDLE_DELAY: Duration = Duration::from_secs(30);
+/// In percent
+pub const DEFAULT_IDLE_OPACITY: u32 = 30;
+
+/// Zoom levels of the page, in percent
+pub const DEFAULT_ZOOM: u32 = 100;
+pub const MIN_ZOOM: u32 = 25;
+pub const MAX_ZOOM: u32 = 500;
+
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "kebab-case")]
+pub enum CombatCondition {
+    #[default]
+    Any,
+    InCombat,
+    OutOfCombat
+}
+
+impl CombatCondition {
+    pub const ALL: [CombatCondition; 3] = [
+        CombatCondition::Any,
+        CombatCondition::InCombat,
+        CombatCondition::OutOfCombat,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            CombatCondition::Any => "In and out of combat",
+            CombatCondition::InCombat => "In combat",
+            CombatCondition::OutOfCombat => "Out of combat",
+        }
+    }
+}
+
+/// The state of the game an overlay is shown in, every condition must match
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct VisibilityConditions {
+    #[serde(default)]
+    pub combat: CombatCondition,
+    /// Any zone when empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub zones: Vec<u32>,
+    /// Job abbreviations, any job when empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub jobs: Vec<String>
+}
+
+/// The settings of an overlay a validation error is shown next to
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ConfigField {
+    Name,
+    Url,
+    Width,
+    Height
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct FieldError {
+    pub field: ConfigField,
+    pub message: String
+}
+
+impl FieldError {
+    fn new(field: ConfigField, message: impl Into<String>) -> Self {
+        Self { field, message: message.into() }
+    }
+}
+
+impl std::fmt::Display for FieldError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+/// The schemes the overlays can be loaded from
+const URL_SCHEMES: [&str; 3] = ["http://", "https://", "file://"];
+
 #[derive(Debug, Default, Serialize, Deserialize, PartialEq, Eq, Clone)]
 pub struct LayoutConfig {
     name: String,
     url: String,
+    /// Folder of the overlay in the sidebar, nested folders are separated by slashes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    folder: Option<String>,
+    /// Free keywords the overlays are searched by
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
+    /// Identifier of the monitor x and y are relative to, absolute coordinates when missing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    monitor: Option<String>,
+    /// Placed relative to the client area of the game window while it is found
+    #[serde(default)]
+    follow_game_window: bool,
+    #[serde(default)]
+    anchor: Anchor,
     x: i32,
     y: i32,
+    #[serde(default)]
+    size_unit: SizeUnit,
     width: i32,
     height: i32,
     clickthrough: bool,
+    #[serde(default)]
+    clickthrough_mode: ClickthroughMode,
+    /// In milliseconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    alpha_sampling_interval: Option<u64>,
     decorated: bool,
+    #[serde(default)]
+    visibility: Visibility,
+    #[serde(default)]
+    conditions: VisibilityConditions,
+    #[serde(default)]
+    idle_behaviour: IdleBehaviour,
+    /// In seconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    idle_delay: Option<u64>,
+    /// Opacity of a faded overlay, in percent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    idle_opacity: Option<u32>,
+    /// Zoom level of the page, in percent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    zoom: Option<u32>,
+    /// Style sheet added to the page
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    user_css: Option<String>,
     active: bool
 }
 
@@ -56,6 +308,30 @@ impl LayoutConfig {
         self.name = name.into()
     }
 
+    pub fn folder(&self) -> Option<String> {
+        self.folder.clone()
+    }
+
+    pub fn set_folder(&mut self, folder: Option<String>) {
+        self.folder = folder.filter(|folder| !folder.is_empty());
+    }
+
+    pub fn tags(&self) -> Vec<String> {
+        self.tags.clone()
+    }
+
+    pub fn set_tags(&mut self, tags: Vec<String>) {
+        self.tags = tags;
+    }
+
+    /// Whether the overlay is in the folder, or in one of its subfolders
+    pub fn is_in_folder(&self, folder: &str) -> bool {
+        self.folder
+            .as_deref()
+            .map(|overlay_folder| overlay_folder == folder || overlay_folder.starts_with(&format!("{}/", folder)))
+            .unwrap_or(false)
+    }
+
     pub fn url(&self) -> String {
         self.url.clone()
     }
@@ -64,6 +340,38 @@ impl LayoutConfig {
         self.url = url.into()
     }
 
+    pub fn monitor(&self) -> Option<String> {
+        self.monitor.clone()
+    }
+
+    pub fn set_monitor(&mut self, monitor: Option<String>) {
+        self.monitor = monitor
+    }
+
+    pub fn follows_game_window(&self) -> bool {
+        self.follow_game_window
+    }
+
+    pub fn set_follow_game_window(&mut self, follow_game_window: bool) {
+        self.follow_game_window = follow_game_window;
+    }
+
+    pub fn anchor(&self) -> Anchor {
+        self.anchor
+    }
+
+    pub fn set_anchor(&mut self, anchor: Anchor) {
+        self.anchor = anchor
+    }
+
+    pub fn size_unit(&self) -> SizeUnit {
+        self.size_unit
+    }
+
+    pub fn set_size_unit(&mut self, size_unit: SizeUnit) {
+        self.size_unit = size_unit
+    }
+
     pub fn x(&self) -> i32 {
         self.x
     }
@@ -112,6 +420,87 @@ impl LayoutConfig {
         self.clickthrough = is_clickthrough
     }
     
+    pub fn clickthrough_mode(&self) -> ClickthroughMode {
+        self.clickthrough_mode
+    }
+
+    pub fn set_clickthrough_mode(&mut self, clickthrough_mode: ClickthroughMode) {
+        self.clickthrough_mode = clickthrough_mode;
+    }
+
+    pub fn alpha_sampling_interval(&self) -> Duration {
+        self.alpha_sampling_interval
+            .map(Duration::from_millis)
+            .unwrap_or(DEFAULT_ALPHA_SAMPLING_INTERVAL)
+    }
+
+    pub fn set_alpha_sampling_interval(&mut self, interval: Duration) {
+        self.alpha_sampling_interval = (interval != DEFAULT_ALPHA_SAMPLING_INTERVAL).then_some(interval.as_millis() as u64);
+    }
+
+    pub fn visibility(&self) -> Visibility {
+        self.visibility
+    }
+
+    pub fn set_visibility(&mut self, visibility: Visibility) {
+        self.visibility = visibility;
+    }
+
+    pub fn conditions(&self) -> &VisibilityConditions {
+        &self.conditions
+    }
+
+    pub fn set_conditions(&mut self, conditions: VisibilityConditions) {
+        self.conditions = conditions;
+    }
+
+    pub fn idle_behaviour(&self) -> IdleBehaviour {
+        self.idle_behaviour
+    }
+
+    pub fn set_idle_behaviour(&mut self, idle_behaviour: IdleBehaviour) {
+        self.idle_behaviour = idle_behaviour;
+    }
+
+    pub fn idle_delay(&self) -> Duration {
+        self.idle_delay
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_IDLE_DELAY)
+    }
+
+    pub fn set_idle_delay(&mut self, delay: Duration) {
+        self.idle_delay = (delay != DEFAULT_IDLE_DELAY).then_some(delay.as_secs());
+    }
+
+    /// Between 0 and 1
+    pub fn idle_opacity(&self) -> f64 {
+        self.idle_opacity.unwrap_or(DEFAULT_IDLE_OPACITY).min(100) as f64 / 100.0
+    }
+
+    pub fn set_idle_opacity(&mut self, opacity: f64) {
+        let opacity = (opacity.clamp(0.0, 1.0) * 100.0).round() as u32;
+        self.idle_opacity = (opacity != DEFAULT_IDLE_OPACITY).then_some(opacity);
+    }
+
+    /// 1 for the page at its own size
+    pub fn zoom(&self) -> f64 {
+        self.zoom.unwrap_or(DEFAULT_ZOOM).clamp(MIN_ZOOM, MAX_ZOOM) as f64 / 100.0
+    }
+
+    pub fn set_zoom(&mut self, zoom: f64) {
+        let zoom = (zoom * 100.0).round().clamp(MIN_ZOOM as f64, MAX_ZOOM as f64) as u32;
+        self.zoom = (zoom != DEFAULT_ZOOM).then_some(zoom);
+    }
+
+    pub fn user_css(&self) -> String {
+        self.user_css.clone().unwrap_or_default()
+    }
+
+    pub fn set_user_css(&mut self, user_css: impl Into<String>) {
+        let user_css = user_css.into();
+        self.user_css = (!user_css.trim().is_empty()).then_some(user_css);
+    }
+
     pub fn is_active(&self) -> bool {
         self.active
     }
@@ -119,6 +508,85 @@ impl LayoutConfig {
     pub fn set_active(&mut self, is_active: bool) {
         self.active = is_active
     }
+
+    /// The settings making the overlay unusable, empty when it can be saved and opened
+    pub fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+
+        if self.name.trim().is_empty() {
+            errors.push(FieldError::new(ConfigField::Name, "The name is required"));
+        } else if self.name.contains('/') {
+            errors.push(FieldError::new(ConfigField::Name, "The name can not contain a slash"));
+        }
+
+        let url = self.url.trim();
+        if url.is_empty() {
+            errors.push(FieldError::new(ConfigField::Url, "The url is required"));
+        } else {
+            let has_address = URL_SCHEMES
+                .iter()
+                .any(|scheme| url.strip_prefix(scheme).map(|address| !address.is_empty()).unwrap_or(false));
+            if !has_address || url.contains(char::is_whitespace) {
+                errors.push(FieldError::new(ConfigField::Url, "The url must start with http://, https:// or file://"));
+            }
+        }
+
+        let max_size = match self.size_unit {
+            SizeUnit::Pixels => i32::MAX,
+            SizeUnit::Percent => 100
+        };
+        for (field, size, label) in [(ConfigField::Width, self.width, "width"), (ConfigField::Height, self.height, "height")] {
+            if size <= 0 {
+                errors.push(FieldError::new(field, format!("The {} must be greater than 0", label)));
+            } else if size > max_size {
+                errors.push(FieldError::new(field, format!("The {} can not be more than 100%", label)));
+            }
+        }
+
+        errors
+    }
+
+    /// The edited overlay, with the fields which were not edited since `baseline` taken from `saved`
+    pub fn merge_unedited(&self, baseline: &LayoutConfig, saved: &LayoutConfig) -> LayoutConfig {
+        let mut merged = self.clone();
+        macro_rules! merge_fields {
+            ($($field:ident),*) => {
+                $(
+                    if self.$field == baseline.$field {
+                        merged.$field = saved.$field.clone();
+                    }
+                )*
+            };
+        }
+        merge_fields!(
+            name, url, folder, tags, monitor, follow_game_window, anchor, x, y, size_unit, width, height,
+            clickthrough, clickthrough_mode, alpha_sampling_interval, decorated, visibility, conditions,
+            idle_behaviour, idle_delay, idle_opacity, zoom, user_css, active
+        );
+        merged
+    }
+
+    /// The saved overlay with the values of the form, the folder and the active state are not in the form
+    pub fn with_form(&self, form: &LayoutConfig) -> LayoutConfig {
+        LayoutConfig {
+            folder: self.folder.clone(),
+            active: self.active,
+            ..form.clone()
+        }
+    }
+
+    /// Validate the overlay before saving it, its name must not be taken by another saved overlay
+    pub fn validate_rename(&self, saved_name: &str) -> Vec<FieldError> {
+        let mut errors = self.validate();
+
+        let saved_file_name = LayoutConfig { name: saved_name.to_string(), ..Default::default() }.get_file_name();
+        let is_name_valid = !errors.iter().any(|error| error.field == ConfigField::Name);
+        if is_name_valid && self.get_file_name() != saved_file_name && overlay_file_exists(&self.get_file_name()) {
+            errors.push(FieldError::new(ConfigField::Name, "Another overlay already has this name"));
+        }
+
+        errors
+    }
 }
 
 pub fn load_layouts() -> Vec<LayoutConfig> {
@@ -150,6 +618,44 @@ pub fn save_overlay(overlay: LayoutConfig) -> Result<(), std::io::Error> {
     std::fs::write(overlay_path, overlay_str.as_bytes())
 }
 
+/// The folders created in the sidebar, none when they were never saved
+pub fn load_created_folders() -> BTreeSet<String> {
+    let app_config = AppConfig::default();
+
+    std::fs::read_to_string(app_config.folders_path())
+        .ok()
+        .and_then(|content| serde_yaml::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_created_folders(folders: &BTreeSet<String>) -> Result<(), std::io::Error> {
+    let app_config = AppConfig::default();
+
+    let folders_str = serde_yaml::to_string(folders)
+        .map_err(|error| std::io::Error::new(std::io::ErrorKind::InvalidData, error))?;
+    std::fs::write(app_config.folders_path(), folders_str.as_bytes())
+}
+
+fn overlay_file_exists(overlay_file_name: &str) -> bool {
+    let app_config = AppConfig::default();
+
+    app_config
+        .layouts_config_path()
+        .join(overlay_file_name)
+        .exists()
+}
+
+/// When the file of the overlay was last written, `None` when it is not saved
+pub fn overlay_modified_time(overlay: &LayoutConfig) -> Option<SystemTime> {
+    let app_config = AppConfig::default();
+
+    let overlay_path = app_config
+        .layouts_config_path()
+        .join(overlay.get_file_name());
+
+    std::fs::metadata(overlay_path).and_then(|metadata| metadata.modified()).ok()
+}
+
 pub fn remove_overlay_file(overlay_file_name: String) -> Result<(), std::io::Error> {
     let app_config = AppConfig::default();
     
@@ -170,4 +676,155 @@ pub fn get_layout_by_name(overlay_name: &str) -> Result<LayoutConfig, String> {
     }
 
     Err("Could not find the overlay !".to_owned())
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn overlay(name: &str, url: &str) -> LayoutConfig {
+        let mut overlay = LayoutConfig::default();
+        overlay.set_name(name);
+        overlay.set_url(url);
+        overlay.set_x(10);
+        overlay.set_y(20);
+        overlay.set_width(300);
+        overlay.set_height(200);
+        overlay
+    }
+
+    #[test]
+    fn a_move_of_an_overlay_edited_in_the_form_is_kept_on_save() {
+        let loaded = overlay("Meter", "https://meter.example/");
+        let baseline = loaded.clone();
+
+        // The url is edited in the form, then the overlay is dragged on screen
+        let mut form = baseline.clone();
+        form.set_url("https://other-meter.example/");
+        let mut moved = loaded.clone();
+        moved.set_folder(Some("Raids".to_string()));
+        moved.set_active(true);
+        moved.set_x(500);
+        moved.set_y(600);
+        moved.set_width(320);
+
+        let edited_form = form.merge_unedited(&baseline, &moved);
+        let baseline = baseline.merge_unedited(&form, &edited_form);
+        let form = edited_form;
+
+        // Saving applies the form to the saved overlay
+        let saved = moved.with_form(&form);
+
+        assert_eq!(saved.x(), 500);
+        assert_eq!(saved.y(), 600);
+        assert_eq!(saved.width(), 320);
+        assert_eq!(saved.url(), "https://other-meter.example/");
+        assert_eq!((saved.folder(), saved.is_active()), (Some("Raids".to_string()), true));
+        // Only the url is still an unsaved change
+        assert_eq!(baseline.x(), 500);
+        assert_eq!(baseline.url(), "https://meter.example/");
+    }
+
+    #[test]
+    fn an_edited_field_keeps_the_edit_when_the_overlay_is_saved_elsewhere() {
+        let baseline = overlay("Meter", "https://meter.example/");
+        let mut form = baseline.clone();
+        form.set_x(42);
+        let mut moved = baseline.clone();
+        moved.set_x(500);
+
+        assert_eq!(form.merge_unedited(&baseline, &moved).x(), 42);
+    }
+
+    fn invalid_fields(overlay: &LayoutConfig) -> Vec<ConfigField> {
+        overlay.validate().into_iter().map(|error| error.field).collect()
+    }
+
+    #[test]
+    fn a_complete_overlay_is_valid() {
+        assert!(overlay("Meter", "https://meter.example/").validate().is_empty());
+        assert!(overlay("Meter", "http://localhost:8080").validate().is_empty());
+        assert!(overlay("Meter", "file:///home/user/meter.html").validate().is_empty());
+    }
+
+    #[test]
+    fn the_name_is_required_without_slashes() {
+        assert_eq!(invalid_fields(&overlay("", "https://meter.example/")), vec![ConfigField::Name]);
+        assert_eq!(invalid_fields(&overlay("  \t", "https://meter.example/")), vec![ConfigField::Name]);
+        assert_eq!(invalid_fields(&overlay("Meters/Main", "https://meter.example/")), vec![ConfigField::Name]);
+    }
+
+    #[test]
+    fn the_url_needs_a_scheme_and_an_address() {
+        assert_eq!(invalid_fields(&overlay("Meter", "")), vec![ConfigField::Url]);
+        assert_eq!(invalid_fields(&overlay("Meter", "meter.example/")), vec![ConfigField::Url]);
+        assert_eq!(invalid_fields(&overlay("Meter", "ftp://meter.example/")), vec![ConfigField::Url]);
+        assert_eq!(invalid_fields(&overlay("Meter", "http://")), vec![ConfigField::Url]);
+        assert_eq!(invalid_fields(&overlay("Meter", "https://meter.example/a page")), vec![ConfigField::Url]);
+    }
+
+    #[test]
+    fn the_size_is_positive_and_at_most_the_whole_area_in_percent() {
+        let mut overlay = overlay("Meter", "https://meter.example/");
+        overlay.set_width(0);
+        overlay.set_height(-20);
+        assert_eq!(invalid_fields(&overlay), vec![ConfigField::Width, ConfigField::Height]);
+
+        overlay.set_size_unit(SizeUnit::Percent);
+        overlay.set_width(100);
+        overlay.set_height(101);
+        assert_eq!(invalid_fields(&overlay), vec![ConfigField::Height]);
+
+        // Pixels are not limited to 100
+        overlay.set_size_unit(SizeUnit::Pixels);
+        assert!(invalid_fields(&overlay).is_empty());
+    }
+
+    #[test]
+    fn the_default_idle_settings_are_not_saved() {
+        let mut overlay = overlay("Meter", "https://meter.example/");
+        overlay.set_idle_delay(Duration::from_secs(10));
+        overlay.set_idle_opacity(0.5);
+        assert_eq!((overlay.idle_delay, overlay.idle_opacity), (Some(10), Some(50)));
+
+        overlay.set_idle_delay(DEFAULT_IDLE_DELAY);
+        overlay.set_idle_opacity(DEFAULT_IDLE_OPACITY as f64 / 100.0);
+        assert_eq!((overlay.idle_delay, overlay.idle_opacity), (None, None));
+        assert_eq!(overlay.idle_delay(), DEFAULT_IDLE_DELAY);
+    }
+
+    #[test]
+    fn the_default_alpha_sampling_interval_is_not_saved() {
+        let mut overlay = overlay("Meter", "https://meter.example/");
+        overlay.set_alpha_sampling_interval(Duration::from_millis(250));
+        assert_eq!(overlay.alpha_sampling_interval, Some(250));
+
+        overlay.set_alpha_sampling_interval(DEFAULT_ALPHA_SAMPLING_INTERVAL);
+        assert_eq!(overlay.alpha_sampling_interval, None);
+        assert_eq!(overlay.alpha_sampling_interval(), DEFAULT_ALPHA_SAMPLING_INTERVAL);
+    }
+
+    #[test]
+    fn the_default_zoom_and_an_empty_style_sheet_are_not_saved() {
+        let mut overlay = overlay("Meter", "https://meter.example/");
+        overlay.set_zoom(1.5);
+        overlay.set_user_css("body { font-size: 14px; }");
+        assert_eq!(overlay.zoom, Some(150));
+        assert_eq!(overlay.user_css(), "body { font-size: 14px; }");
+
+        overlay.set_zoom(1.0);
+        overlay.set_user_css("  \n");
+        assert_eq!((overlay.zoom, overlay.user_css.as_deref()), (None, None));
+        assert_eq!(overlay.zoom(), 1.0);
+    }
+
+    #[test]
+    fn the_zoom_is_kept_within_its_range() {
+        let mut overlay = overlay("Meter", "https://meter.example/");
+        overlay.set_zoom(10.0);
+        assert_eq!(overlay.zoom(), MAX_ZOOM as f64 / 100.0);
+
+        overlay.set_zoom(0.0);
+        assert_eq!(overlay.zoom(), MIN_ZOOM as f64 / 100.0);
+    }
+}
@@ -2,11 +2,13 @@ extern crate pretty_env_logger;
 #[macro_use] extern crate log;
 
 mod layout_config;
+mod catalog;
 mod overlay;
 mod app;
 mod app_config;
 mod ui;
 mod errors;
+mod relay;
 
 use app::App;
 use gdk::Screen;
@@ -46,11 +48,37 @@ fn main() {
         while let Ok(event) = receiver.recv().await {
             match event {
                 app::AppAction::LoadOverlaysList => app.load_overlays_list(),
-                app::AppAction::SelectOverlay(overlay) => app.display_overlay_details(overlay),
-                app::AppAction::ToggleOverlay(new_state, overlay) => app.toggle_overlay(new_state, overlay),
+                app::AppAction::SelectOverlay(overlay) => app.select_overlay(overlay),
+                app::AppAction::OverlayFormChanged => app.refresh_form_state(),
+                app::AppAction::RevertOverlay => app.revert_overlay(),
+                app::AppAction::ToggleOverlay(new_state, overlay, save_form) => app.toggle_overlay(new_state, overlay, save_form),
+                app::AppAction::ToggleFolder(new_state, folder) => app.toggle_folder(new_state, folder),
+                app::AppAction::MoveToFolder(overlay_name, folder) => app.move_to_folder(overlay_name, folder),
                 app::AppAction::SaveOverlay(mut overlay) => app.save_overlay(&mut overlay),
                 app::AppAction::DeleteOverlay(overlay) => app.delete_overlay(&overlay),
+                app::AppAction::DuplicateOverlay(overlay) => app.duplicate_overlay(overlay),
+                app::AppAction::ReloadOverlay(overlay_name) => app.reload_overlay(overlay_name),
+                app::AppAction::InspectOverlay(overlay_name) => app.inspect_overlay(overlay_name),
                 app::AppAction::NewOverlay => app.new_overlay(),
+                app::AppAction::StartRecording => app.start_recording(),
+                app::AppAction::StopRecording => app.stop_recording(),
+                app::AppAction::StartReplay(path) => app.start_replay(path),
+                app::AppAction::StartSynthetic(config) => app.start_synthetic(config),
+                app::AppAction::UseLiveEvents => app.use_live_events(),
+                app::AppAction::PauseReplay(is_paused) => app.pause_replay(is_paused),
+                app::AppAction::SetReplaySpeed(speed) => app.set_replay_speed(speed),
+                app::AppAction::SeekReplay(position) => app.seek_replay(position),
+                app::AppAction::EventSourceChanged(status) => app.display_event_source(status),
+                app::AppAction::OverlayStatusChanged(overlay_name, status) => app.set_overlay_status(overlay_name, status),
+                app::AppAction::UpstreamStatusChanged(is_connected) => app.set_upstream_status(is_connected),
+                app::AppAction::SetEditMode(settings) => app.set_edit_mode(settings),
+                app::AppAction::OverlayMoved(overlay_name, bounds) => app.move_overlay(overlay_name, bounds),
+                app::AppAction::OpenLayoutEditor => app.open_layout_editor(),
+                app::AppAction::LayoutEdited(edits) => app.edit_layout(edits),
+                app::AppAction::GameWindowChanged(game_area) => app.set_game_area(game_area),
+                app::AppAction::FocusChanged(focused_window) => app.set_focused_window(focused_window),
+                app::AppAction::GameStateChanged(game_state) => app.set_game_state(game_state),
+                app::AppAction::Quit => app.quit(),
             }
         }
     };
@@ -0,0 +1,178 @@
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::time::Duration;
+
+use glib::SourceId;
+use gtk::prelude::*;
+use webkit2gtk::{traits::WebViewExt, SnapshotOptions, SnapshotRegion, WebView};
+
+use super::input_shape::InputShape;
+
+/// The snapshot is read by blocks, a block with a single visible pixel catches the mouse
+const BLOCK_SIZE: i32 = 4;
+
+/// Reads the rendering of the page regularly to let the mouse go through its transparent parts
+pub struct AlphaShape {
+    webview: WebView,
+    input_shape: Rc<InputShape>,
+    is_pending: Cell<bool>,
+    timer: RefCell<Option<SourceId>>
+}
+
+impl AlphaShape {
+    pub fn start(webview: &WebView, input_shape: Rc<InputShape>, interval: Duration) -> Rc<Self> {
+        let alpha_shape = Rc::new(Self {
+            webview: webview.clone(),
+            input_shape,
+            is_pending: Cell::new(false),
+            timer: RefCell::new(None)
+        });
+
+        let alpha_shape_cloned = alpha_shape.clone();
+        *alpha_shape.timer.borrow_mut() = Some(glib::timeout_add_local(interval, move || {
+            alpha_shape_cloned.sample();
+            glib::Continue(true)
+        }));
+
+        alpha_shape
+    }
+
+    pub fn stop(&self) {
+        if let Some(timer) = self.timer.borrow_mut().take() {
+            timer.remove();
+        }
+    }
+
+    fn sample(self: &Rc<Self>) {
+        // A hidden overlay is not rendered, and a slow snapshot must not pile up
+        if self.is_pending.get() || !self.webview.is_visible() || !self.webview.is_mapped() {
+            return;
+        }
+        self.is_pending.set(true);
+
+        let alpha_shape = self.clone();
+        self.webview.snapshot(
+            SnapshotRegion::Visible,
+            SnapshotOptions::TRANSPARENT_BACKGROUND,
+            None::<&gio::Cancellable>,
+            move |result| {
+                alpha_shape.is_pending.set(false);
+
+                let surface = match result.map(cairo::ImageSurface::try_from) {
+                    Ok(Ok(surface)) => surface,
+                    Ok(Err(_)) => return,
+                    Err(error) => {
+                        warn!("Could not read the rendering of the overlay: {}", error);
+                        return;
+                    }
+                };
+
+                if let Some(region) = opaque_region(&surface) {
+                    alpha_shape.input_shape.set_opaque_region(region);
+                }
+            }
+        );
+    }
+}
+
+/// The region covering the pixels which are not fully transparent, in widget coordinates
+fn opaque_region(surface: &cairo::ImageSurface) -> Option<cairo::Region> {
+    if surface.format() != cairo::Format::ARgb32 {
+        return None;
+    }
+
+    let (width, height, stride) = (surface.width(), surface.height(), surface.stride());
+    // The snapshot is in device pixels on HiDPI screens
+    let (scale_x, scale_y) = surface.device_scale();
+    let to_widget_rectangle = |x: i32, y: i32, rectangle_width: i32, rectangle_height: i32| cairo::RectangleInt::new(
+        (x as f64 / scale_x).floor() as i32,
+        (y as f64 / scale_y).floor() as i32,
+        (rectangle_width as f64 / scale_x).ceil() as i32,
+        (rectangle_height as f64 / scale_y).ceil() as i32
+    );
+
+    let region = cairo::Region::create();
+    let result = surface.with_data(|data| {
+        let is_opaque_pixel = |x: i32, y: i32| {
+            let offset = (y * stride + x * 4) as usize;
+            let pixel = u32::from_ne_bytes([data[offset], data[offset + 1], data[offset + 2], data[offset + 3]]);
+            pixel >> 24 != 0
+        };
+        let is_opaque_block = |block_x: i32, block_y: i32| {
+            (block_y..(block_y + BLOCK_SIZE).min(height))
+                .any(|y| (block_x..(block_x + BLOCK_SIZE).min(width)).any(|x| is_opaque_pixel(x, y)))
+        };
+
+        // Consecutive opaque blocks of a row are merged in a single rectangle
+        for block_y in (0..height).step_by(BLOCK_SIZE as usize) {
+            let block_height = BLOCK_SIZE.min(height - block_y);
+            let mut run_start = None;
+
+            for block_x in (0..width).step_by(BLOCK_SIZE as usize) {
+                match (is_opaque_block(block_x, block_y), run_start) {
+                    (true, None) => run_start = Some(block_x),
+                    (false, Some(start)) => {
+                        let _ = region.union_rectangle(&to_widget_rectangle(start, block_y, block_x - start, block_height));
+                        run_start = None;
+                    },
+                    _ => {}
+                }
+            }
+
+            if let Some(start) = run_start {
+                let _ = region.union_rectangle(&to_widget_rectangle(start, block_y, width - start, block_height));
+            }
+        }
+    });
+
+    result.ok().map(|_| region)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A surface of 10×6 pixels with the given rectangles painted opaque
+    fn surface(opaque_rectangles: &[(f64, f64, f64, f64)]) -> cairo::ImageSurface {
+        let surface = cairo::ImageSurface::create(cairo::Format::ARgb32, 10, 6).unwrap();
+        let context = cairo::Context::new(&surface).unwrap();
+        context.set_source_rgba(0.2, 0.4, 0.6, 1.0);
+        for (x, y, width, height) in opaque_rectangles {
+            context.rectangle(*x, *y, *width, *height);
+        }
+        context.fill().unwrap();
+        drop(context);
+        surface
+    }
+
+    fn extents(region: &cairo::Region) -> cairo::RectangleInt {
+        let mut extents = cairo::RectangleInt::new(0, 0, 0, 0);
+        region.extents(&mut extents);
+        extents
+    }
+
+    #[test]
+    fn a_transparent_surface_has_no_opaque_region() {
+        let region = opaque_region(&surface(&[])).unwrap();
+        assert!(region.is_empty());
+    }
+
+    #[test]
+    fn an_opaque_surface_is_covered_entirely() {
+        let region = opaque_region(&surface(&[(0.0, 0.0, 10.0, 6.0)])).unwrap();
+        assert_eq!(extents(&region), cairo::RectangleInt::new(0, 0, 10, 6));
+        assert_eq!(region.contains_rectangle(&cairo::RectangleInt::new(0, 0, 10, 6)), cairo::RegionOverlap::In);
+    }
+
+    #[test]
+    fn an_opaque_pixel_covers_its_block() {
+        let region = opaque_region(&surface(&[(5.0, 1.0, 1.0, 1.0)])).unwrap();
+        assert_eq!(extents(&region), cairo::RectangleInt::new(4, 0, BLOCK_SIZE, BLOCK_SIZE));
+    }
+
+    #[test]
+    fn only_the_argb_surfaces_are_read() {
+        let surface = cairo::ImageSurface::create(cairo::Format::Rgb24, 10, 6).unwrap();
+        assert!(opaque_region(&surface).is_none());
+    }
+}
@@ -0,0 +1,23 @@
+// Reports to xiv-overlay that the page receives data on its websockets, so an idle overlay
+// fades back in. The reports are throttled, a busy meter receives many events per second.
+(() => {
+    const THROTTLE_DELAY = 1000;
+
+    let lastReport = 0;
+
+    function reportActivity() {
+        const now = Date.now();
+        if (now - lastReport >= THROTTLE_DELAY) {
+            lastReport = now;
+            window.webkit.messageHandlers.xivOverlayActivity.postMessage('');
+        }
+    }
+
+    const NativeWebSocket = window.WebSocket;
+    window.WebSocket = class extends NativeWebSocket {
+        constructor(...args) {
+            super(...args);
+            this.addEventListener('message', reportActivity);
+        }
+    };
+})();
@@ -0,0 +1,302 @@
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+use async_channel::Sender;
+use gtk::prelude::*;
+
+use crate::app::AppAction;
+use crate::layout_config::LayoutConfig;
+
+use super::input_shape::InputShape;
+use super::layer_shell::layer_shell;
+use super::placement;
+use super::snapping::{snap, Bounds, Guide, SnapTargets};
+
+/// The layout edition options chosen in the manager
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EditSettings {
+    pub grid: Option<i32>
+}
+
+/// What an overlay needs to know to be moved around in the edit state
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EditContext {
+    pub grid: Option<i32>,
+    pub other_overlays: Vec<Bounds>
+}
+
+/// Where the drag started, in root coordinates
+#[derive(Clone, Copy)]
+struct Drag {
+    pointer_origin: (f64, f64),
+    window_origin: (i32, i32)
+}
+
+/// Lets the overlay be dragged with the mouse, snapping it and drawing guides
+pub struct EditMode {
+    overlay_name: String,
+    window: gtk::Window,
+    config: Rc<RefCell<LayoutConfig>>,
+    game_area: Rc<Cell<Option<Bounds>>>,
+    input_shape: Rc<InputShape>,
+    event_sender: Sender<AppAction>,
+    drag_area: gtk::EventBox,
+    guides_window: gtk::Window,
+    guides: RefCell<Vec<Guide>>,
+    context: RefCell<Option<EditContext>>,
+    drag: Cell<Option<Drag>>
+}
+
+impl EditMode {
+    pub fn new(
+        window: &gtk::Window,
+        config: Rc<RefCell<LayoutConfig>>,
+        game_area: Rc<Cell<Option<Bounds>>>,
+        input_shape: Rc<InputShape>,
+        event_sender: Sender<AppAction>
+    ) -> Rc<Self> {
+        let drag_area = gtk::EventBox::new();
+        drag_area.set_visible_window(false);
+        drag_area.set_no_show_all(true);
+        drag_area.add_events(
+            gdk::EventMask::BUTTON_PRESS_MASK
+                | gdk::EventMask::BUTTON_RELEASE_MASK
+                | gdk::EventMask::POINTER_MOTION_MASK
+        );
+
+        let overlay_name = config.borrow().name();
+        let edit_mode = Rc::new(Self {
+            overlay_name,
+            window: window.clone(),
+            config,
+            game_area,
+            input_shape,
+            event_sender,
+            drag_area,
+            guides_window: EditMode::create_guides_window(),
+            guides: RefCell::new(Vec::new()),
+            context: RefCell::new(None),
+            drag: Cell::new(None)
+        });
+        edit_mode.setup_signals();
+
+        edit_mode
+    }
+
+    /// The widget catching the mouse, to be put above the webview
+    pub fn drag_area(&self) -> &gtk::EventBox {
+        &self.drag_area
+    }
+
+    pub fn is_editing(&self) -> bool {
+        self.context.borrow().is_some()
+    }
+
+    pub fn set_context(&self, context: Option<EditContext>) {
+        let is_editing = context.is_some();
+        *self.context.borrow_mut() = context;
+        self.input_shape.set_editing(is_editing);
+
+        if is_editing {
+            self.drag_area.show();
+        } else {
+            self.drag_area.hide();
+            self.end_drag();
+        }
+        self.drag_area.queue_draw();
+    }
+
+    pub fn close(&self) {
+        self.guides_window.close();
+    }
+
+    fn create_guides_window() -> gtk::Window {
+        let guides_window = gtk::Window::new(gtk::WindowType::Popup);
+        if let Some(visual) = GtkWindowExt::screen(&guides_window).and_then(|screen| screen.rgba_visual()) {
+            guides_window.set_visual(Some(&visual));
+        }
+        guides_window.set_app_paintable(true);
+        // The guides must never catch the mouse
+        guides_window.input_shape_combine_region(Some(&cairo::Region::create()));
+        guides_window
+    }
+
+    fn setup_signals(self: &Rc<Self>) {
+        let edit_mode = self.clone();
+        self.drag_area.connect_draw(move |area, ctx| {
+            let width = area.allocated_width() as f64;
+            let height = area.allocated_height() as f64;
+
+            ctx.set_source_rgba(0.2, 0.5, 1.0, 0.25);
+            let _ = ctx.paint();
+
+            ctx.set_source_rgba(0.2, 0.5, 1.0, 0.9);
+            ctx.set_line_width(2.0);
+            ctx.set_dash(&[6.0, 4.0], 0.0);
+            ctx.rectangle(1.0, 1.0, width - 2.0, height - 2.0);
+            let _ = ctx.stroke();
+
+            ctx.set_source_rgba(1.0, 1.0, 1.0, 1.0);
+            ctx.set_font_size(14.0);
+            ctx.move_to(8.0, 20.0);
+            let _ = ctx.show_text(&edit_mode.overlay_name);
+            Inhibit(false)
+        });
+
+        let edit_mode = self.clone();
+        self.drag_area.connect_button_press_event(move |_, event| {
+            if event.button() == 1 {
+                edit_mode.start_drag(event.root());
+            }
+            Inhibit(true)
+        });
+
+        let edit_mode = self.clone();
+        self.drag_area.connect_motion_notify_event(move |_, event| {
+            edit_mode.drag_to(event.root());
+            Inhibit(true)
+        });
+
+        let edit_mode = self.clone();
+        self.drag_area.connect_button_release_event(move |_, event| {
+            if event.button() == 1 {
+                edit_mode.end_drag();
+            }
+            Inhibit(true)
+        });
+
+        let edit_mode = self.clone();
+        self.guides_window.connect_draw(move |window, ctx| {
+            ctx.set_operator(cairo::Operator::Source);
+            ctx.set_source_rgba(0.0, 0.0, 0.0, 0.0);
+            let _ = ctx.paint();
+            ctx.set_operator(cairo::Operator::Over);
+
+            let (origin_x, origin_y) = window.position();
+            let width = window.allocated_width() as f64;
+            let height = window.allocated_height() as f64;
+
+            // The grid is drawn faintly, the guides of the snapped lines on top of it
+            if let Some(grid) = edit_mode.context.borrow().as_ref().and_then(|context| context.grid) {
+                ctx.set_source_rgba(1.0, 1.0, 1.0, 0.1);
+                ctx.set_line_width(1.0);
+                for x in (0..width as i32).step_by(grid.max(1) as usize) {
+                    ctx.move_to(x as f64 + 0.5, 0.0);
+                    ctx.line_to(x as f64 + 0.5, height);
+                }
+                for y in (0..height as i32).step_by(grid.max(1) as usize) {
+                    ctx.move_to(0.0, y as f64 + 0.5);
+                    ctx.line_to(width, y as f64 + 0.5);
+                }
+                let _ = ctx.stroke();
+            }
+
+            ctx.set_source_rgba(1.0, 0.3, 0.6, 0.9);
+            ctx.set_line_width(1.0);
+            for guide in edit_mode.guides.borrow().iter() {
+                match guide {
+                    Guide::Vertical(x) => {
+                        ctx.move_to((x - origin_x) as f64 + 0.5, 0.0);
+                        ctx.line_to((x - origin_x) as f64 + 0.5, height);
+                    },
+                    Guide::Horizontal(y) => {
+                        ctx.move_to(0.0, (y - origin_y) as f64 + 0.5);
+                        ctx.line_to(width, (y - origin_y) as f64 + 0.5);
+                    }
+                }
+            }
+            let _ = ctx.stroke();
+            Inhibit(false)
+        });
+    }
+
+    fn window_position(&self) -> (i32, i32) {
+        match layer_shell() {
+            Some(layer_shell) => layer_shell.window_position(&self.window, &self.config.borrow()),
+            None => self.window.position()
+        }
+    }
+
+    fn monitor_bounds(&self) -> Vec<Bounds> {
+        placement::monitors(&WidgetExt::display(&self.window))
+            .iter()
+            .map(|monitor| Bounds::from_rectangle(&monitor.geometry))
+            .collect()
+    }
+
+    fn start_drag(&self, pointer_origin: (f64, f64)) {
+        self.drag.set(Some(Drag { pointer_origin, window_origin: self.window_position() }));
+
+        // Cover the whole screen to draw the guides anywhere
+        let monitors = self.monitor_bounds();
+        if let Some(first) = monitors.first() {
+            let screen_area = monitors
+                .iter()
+                .skip(1)
+                .fold(first.to_rectangle(), |area, monitor| area.union(&monitor.to_rectangle()));
+            self.guides_window.move_(screen_area.x(), screen_area.y());
+            self.guides_window.resize(screen_area.width(), screen_area.height());
+        }
+        self.guides_window.show_all();
+    }
+
+    fn drag_to(&self, pointer: (f64, f64)) {
+        let Some(drag) = self.drag.get() else {
+            return;
+        };
+        let Some(context) = self.context.borrow().clone() else {
+            return;
+        };
+
+        let (width, height) = self.window.size();
+        let bounds = Bounds {
+            x: drag.window_origin.0 + (pointer.0 - drag.pointer_origin.0) as i32,
+            y: drag.window_origin.1 + (pointer.1 - drag.pointer_origin.1) as i32,
+            width,
+            height
+        };
+        // The edges of the game window are snapped to like the monitor ones
+        let mut monitors = self.monitor_bounds();
+        monitors.extend(self.game_area.get());
+        let targets = SnapTargets {
+            monitors,
+            overlays: context.other_overlays,
+            grid: context.grid
+        };
+        let (snapped_bounds, guides) = snap(bounds, &targets);
+
+        match layer_shell() {
+            Some(layer_shell) => layer_shell.move_window(&self.window, &mut self.config.borrow_mut(), snapped_bounds.x, snapped_bounds.y),
+            None => self.window.move_(snapped_bounds.x, snapped_bounds.y)
+        }
+        *self.guides.borrow_mut() = guides;
+        self.guides_window.queue_draw();
+    }
+
+    fn end_drag(&self) {
+        if self.drag.take().is_none() {
+            return;
+        }
+        self.guides.borrow_mut().clear();
+        self.guides_window.hide();
+
+        let (x, y) = self.window_position();
+        let (width, height) = self.window.size();
+        let bounds = Bounds { x, y, width, height };
+
+        // Keep the config of the window in sync, it is used to place it again when the monitors change
+        let game_area = self.game_area.get().map(Bounds::to_rectangle);
+        placement::move_config_to(
+            &mut self.config.borrow_mut(),
+            &bounds.to_rectangle(),
+            &WidgetExt::display(&self.window),
+            game_area.as_ref()
+        );
+
+        let event_sender = self.event_sender.clone();
+        let overlay_name = self.overlay_name.clone();
+        glib::MainContext::default().spawn_local(async move {
+            let _ = event_sender.send(AppAction::OverlayMoved(overlay_name, bounds)).await;
+        });
+    }
+}
@@ -0,0 +1,234 @@
+use std::time::Duration;
+
+use async_channel::Sender;
+use gdk::prelude::*;
+
+use crate::app::AppAction;
+
+use super::snapping::Bounds;
+
+/// The game does not tell when it moves, its window is looked up regularly
+const TRACKING_INTERVAL: Duration = Duration::from_millis(500);
+
+/// WM_WINDOW_ROLE of the manager window, to recognize it when it is focused
+pub const MANAGER_ROLE: &str = "xiv-overlay-manager";
+/// WM_WINDOW_ROLE of the overlay windows
+pub const OVERLAY_ROLE: &str = "xiv-overlay";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FocusedWindow {
+    /// The game, or one of the overlays drawn over it
+    Game,
+    Manager,
+    Other
+}
+
+/// How the game window is recognized among the X11 windows
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GameWindowRule {
+    /// The instance or class name of WM_CLASS, ignoring the case
+    WmClass(String),
+    /// A part of the window title
+    Title(String)
+}
+
+impl Default for GameWindowRule {
+    fn default() -> Self {
+        GameWindowRule::WmClass("ffxiv_dx11.exe".to_string())
+    }
+}
+
+impl GameWindowRule {
+    /// Parse a rule written as `class:<name>` or `title:<text>`, a bare value is a class
+    pub fn parse(rule: &str) -> Self {
+        match rule.split_once(':') {
+            Some(("title", title)) => GameWindowRule::Title(title.to_string()),
+            Some(("class", class)) => GameWindowRule::WmClass(class.to_string()),
+            _ => GameWindowRule::WmClass(rule.to_string())
+        }
+    }
+
+    fn matches(&self, window: &gdk::Window) -> bool {
+        match self {
+            GameWindowRule::WmClass(_) => self.matches_properties(&wm_class(window), None),
+            GameWindowRule::Title(_) => self.matches_properties(&[], window_title(window).as_deref())
+        }
+    }
+
+    fn matches_properties(&self, wm_class: &[String], window_title: Option<&str>) -> bool {
+        match self {
+            GameWindowRule::WmClass(class) => wm_class
+                .iter()
+                .any(|name| name.eq_ignore_ascii_case(class)),
+            GameWindowRule::Title(title) => window_title
+                .map(|window_title| window_title.contains(title.as_str()))
+                .unwrap_or(false)
+        }
+    }
+}
+
+/// Look for the game window regularly and report the position of its client area
+/// and whether it is focused when they change
+pub fn track_game_window(rule: GameWindowRule, event_sender: Sender<AppAction>) {
+    let Some(display) = gdk::Display::default() else {
+        return;
+    };
+    // The windows of other clients can only be inspected on X11 (or XWayland)
+    if display.type_().name() != "GdkX11Display" {
+        info!("Not running on X11, the overlays can not follow the game window");
+        return;
+    }
+
+    let mut last_area: Option<Bounds> = None;
+    let mut last_focus: Option<FocusedWindow> = None;
+    glib::timeout_add_local(TRACKING_INTERVAL, move || {
+        let focus = find_focused_window(&rule);
+        if focus != last_focus {
+            last_focus = focus;
+
+            let event_sender = event_sender.clone();
+            glib::MainContext::default().spawn_local(async move {
+                let _ = event_sender.send(AppAction::FocusChanged(focus)).await;
+            });
+        }
+
+        let area = find_game_area(&rule);
+        if area != last_area {
+            match area {
+                Some(area) => info!("Game window found at {:?}", area),
+                None if last_area.is_some() => info!("The game window is gone"),
+                None => {}
+            }
+            last_area = area;
+
+            let event_sender = event_sender.clone();
+            glib::MainContext::default().spawn_local(async move {
+                let _ = event_sender.send(AppAction::GameWindowChanged(area)).await;
+            });
+        }
+
+        glib::Continue(true)
+    });
+}
+
+fn find_game_area(rule: &GameWindowRule) -> Option<Bounds> {
+    let screen = gdk::Screen::default()?;
+    screen
+        .window_stack()
+        .into_iter()
+        .filter(|window| rule.matches(window))
+        .find_map(|window| window_area(&window))
+}
+
+/// The origin and geometry are queried from the server, they are never stale
+fn window_area(window: &gdk::Window) -> Option<Bounds> {
+    // The window of another client can be destroyed at any time, which would abort on the X error
+    gdk::error_trap_push();
+    let (_, x, y) = window.origin();
+    let (_, _, width, height) = window.geometry();
+    if gdk::error_trap_pop() != 0 || width <= 1 || height <= 1 {
+        return None;
+    }
+
+    Some(Bounds { x, y, width, height })
+}
+
+/// What _NET_ACTIVE_WINDOW points to, unknown when the window manager does not support it
+fn find_focused_window(rule: &GameWindowRule) -> Option<FocusedWindow> {
+    let screen = gdk::Screen::default()?;
+    // Without the hint, no window would ever look focused
+    read_property(&screen.root_window()?, "_NET_ACTIVE_WINDOW")?;
+
+    let Some(window) = active_window(&screen) else {
+        return Some(FocusedWindow::Other);
+    };
+
+    let focus = match read_string_property(&window, "WM_WINDOW_ROLE").as_deref() {
+        Some(MANAGER_ROLE) => FocusedWindow::Manager,
+        // Clicking on an overlay is still playing the game
+        Some(OVERLAY_ROLE) => FocusedWindow::Game,
+        _ if rule.matches(&window) => FocusedWindow::Game,
+        _ => FocusedWindow::Other
+    };
+
+    Some(focus)
+}
+
+/// gdk_screen_get_active_window is deprecated and not exposed by the bindings
+fn active_window(screen: &gdk::Screen) -> Option<gdk::Window> {
+    use glib::translate::{from_glib_full, ToGlibPtr};
+
+    unsafe { from_glib_full(gdk_sys::gdk_screen_get_active_window(screen.to_glib_none().0)) }
+}
+
+/// The instance and class names of a window
+fn wm_class(window: &gdk::Window) -> Vec<String> {
+    read_string_property(window, "WM_CLASS")
+        .map(|names| names
+            .split('\0')
+            .filter(|name| !name.is_empty())
+            .map(str::to_string)
+            .collect())
+        .unwrap_or_default()
+}
+
+fn window_title(window: &gdk::Window) -> Option<String> {
+    read_string_property(window, "_NET_WM_NAME").or_else(|| read_string_property(window, "WM_NAME"))
+}
+
+fn read_property(window: &gdk::Window, property: &str) -> Option<Vec<u8>> {
+    // A window destroyed meanwhile has no properties, the X error is not fatal
+    gdk::error_trap_push();
+    let property = gdk::property_get(
+        window,
+        &gdk::Atom::intern(property),
+        &gdk::ATOM_NONE,
+        0,
+        1024,
+        0
+    );
+    gdk::error_trap_pop_ignored();
+    let (_, _, data) = property?;
+
+    Some(data)
+}
+
+fn read_string_property(window: &gdk::Window, property: &str) -> Option<String> {
+    let data = read_property(window, property)?;
+
+    // X11 strings are sent with their terminating null character
+    Some(String::from_utf8_lossy(&data).trim_end_matches('\0').to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_the_rules() {
+        assert_eq!(GameWindowRule::parse("class:ffxiv.exe"), GameWindowRule::WmClass("ffxiv.exe".to_string()));
+        assert_eq!(GameWindowRule::parse("title:FINAL FANTASY XIV"), GameWindowRule::Title("FINAL FANTASY XIV".to_string()));
+        assert_eq!(GameWindowRule::parse("ffxiv.exe"), GameWindowRule::WmClass("ffxiv.exe".to_string()));
+        // Only the first colon separates the kind of rule
+        assert_eq!(GameWindowRule::parse("title:a:b"), GameWindowRule::Title("a:b".to_string()));
+        assert_eq!(GameWindowRule::parse("other:a"), GameWindowRule::WmClass("other:a".to_string()));
+    }
+
+    #[test]
+    fn matches_the_class_ignoring_the_case() {
+        let rule = GameWindowRule::default();
+        let wm_class = vec!["ffxiv_dx11.exe".to_string(), "FFXIV_DX11.EXE".to_string()];
+        assert!(rule.matches_properties(&wm_class, None));
+        assert!(GameWindowRule::WmClass("Ffxiv_Dx11.exe".to_string()).matches_properties(&wm_class, None));
+        assert!(!rule.matches_properties(&["steam.exe".to_string()], Some("ffxiv_dx11.exe")));
+        assert!(!rule.matches_properties(&[], None));
+    }
+
+    #[test]
+    fn matches_a_part_of_the_title() {
+        let rule = GameWindowRule::Title("FINAL FANTASY".to_string());
+        assert!(rule.matches_properties(&[], Some("FINAL FANTASY XIV")));
+        assert!(!rule.matches_properties(&[], Some("final fantasy xiv")));
+        assert!(!rule.matches_properties(&["FINAL FANTASY".to_string()], None));
+    }
+}
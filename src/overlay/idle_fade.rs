@@ -0,0 +1,145 @@
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use glib::SourceId;
+use gtk::prelude::*;
+
+use crate::layout_config::{IdleBehaviour, LayoutConfig};
+
+use super::{input_shape::InputShape, interaction::is_under_pointer};
+
+const IDLE_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+const FADE_DURATION: Duration = Duration::from_millis(300);
+const FRAME_INTERVAL: Duration = Duration::from_millis(16);
+
+/// Fades an overlay out once no data or pointer activity happened for a while, and back in on activity
+pub struct IdleFade {
+    window: gtk::Window,
+    input_shape: Rc<InputShape>,
+    is_hiding: bool,
+    delay: Duration,
+    idle_opacity: f64,
+    pointer: Option<gdk::Device>,
+    /// Where the pointer was at the previous check, it moving over the overlay is activity
+    last_pointer_position: Cell<Option<(i32, i32)>>,
+    last_activity: Cell<Instant>,
+    is_idle: Cell<bool>,
+    /// The overlay stays opaque while it is edited
+    is_suspended: Cell<bool>,
+    timer: RefCell<Option<SourceId>>,
+    animation: RefCell<Option<SourceId>>
+}
+
+impl IdleFade {
+    /// Nothing to do for the overlays staying visible
+    pub fn start(window: &gtk::Window, input_shape: Rc<InputShape>, config: &LayoutConfig) -> Option<Rc<Self>> {
+        let idle_opacity = match config.idle_behaviour() {
+            IdleBehaviour::Stay => return None,
+            IdleBehaviour::Fade => config.idle_opacity(),
+            IdleBehaviour::Hide => 0.0
+        };
+
+        let idle_fade = Rc::new(Self {
+            window: window.clone(),
+            input_shape,
+            is_hiding: config.idle_behaviour() == IdleBehaviour::Hide,
+            delay: config.idle_delay(),
+            idle_opacity,
+            pointer: WidgetExt::display(window).default_seat().and_then(|seat| seat.pointer()),
+            last_pointer_position: Cell::new(None),
+            last_activity: Cell::new(Instant::now()),
+            is_idle: Cell::new(false),
+            is_suspended: Cell::new(false),
+            timer: RefCell::new(None),
+            animation: RefCell::new(None)
+        });
+
+        let idle_fade_cloned = idle_fade.clone();
+        *idle_fade.timer.borrow_mut() = Some(glib::timeout_add_local(IDLE_CHECK_INTERVAL, move || {
+            idle_fade_cloned.check();
+            glib::Continue(true)
+        }));
+
+        Some(idle_fade)
+    }
+
+    pub fn stop(&self) {
+        if let Some(timer) = self.timer.borrow_mut().take() {
+            timer.remove();
+        }
+        if let Some(animation) = self.animation.borrow_mut().take() {
+            animation.remove();
+        }
+    }
+
+    fn check(self: &Rc<Self>) {
+        // Clickthrough and hidden overlays get no motion events, the pointer is polled instead
+        if let Some(pointer) = &self.pointer {
+            let (_, x, y) = pointer.position();
+            let has_moved = self.last_pointer_position.replace(Some((x, y))) != Some((x, y));
+            if has_moved && is_under_pointer(&self.window, Some(pointer)) {
+                self.on_activity();
+                return;
+            }
+        }
+
+        if !self.is_idle.get() && self.last_activity.get().elapsed() >= self.delay {
+            self.is_idle.set(true);
+            self.apply();
+        }
+    }
+
+    pub fn on_activity(self: &Rc<Self>) {
+        self.last_activity.set(Instant::now());
+        if self.is_idle.replace(false) {
+            self.apply();
+        }
+    }
+
+    pub fn set_suspended(self: &Rc<Self>, is_suspended: bool) {
+        if self.is_suspended.replace(is_suspended) != is_suspended {
+            // The delay starts over once the edition is done
+            self.last_activity.set(Instant::now());
+            self.is_idle.set(false);
+            self.apply();
+        }
+    }
+
+    fn apply(self: &Rc<Self>) {
+        let is_faded = self.is_idle.get() && !self.is_suspended.get();
+        let target_opacity = if is_faded { self.idle_opacity } else { 1.0 };
+
+        // The overlay catches the mouse again as soon as it fades in
+        if !is_faded {
+            self.input_shape.set_hidden(false);
+        }
+
+        if let Some(animation) = self.animation.borrow_mut().take() {
+            animation.remove();
+        }
+
+        let step = FRAME_INTERVAL.as_secs_f64() / FADE_DURATION.as_secs_f64();
+        let idle_fade = self.clone();
+        *self.animation.borrow_mut() = Some(glib::timeout_add_local(FRAME_INTERVAL, move || {
+            let opacity = idle_fade.window.opacity();
+            let next_opacity = if opacity < target_opacity {
+                (opacity + step).min(target_opacity)
+            } else {
+                (opacity - step).max(target_opacity)
+            };
+            idle_fade.window.set_opacity(next_opacity);
+
+            if next_opacity != target_opacity {
+                return glib::Continue(true);
+            }
+
+            // A hidden overlay must not block the clicks
+            if is_faded && idle_fade.is_hiding {
+                idle_fade.input_shape.set_hidden(true);
+            }
+            idle_fade.animation.borrow_mut().take();
+            glib::Continue(false)
+        }));
+    }
+}
@@ -0,0 +1,100 @@
+use std::cell::{Cell, RefCell};
+
+use gtk::prelude::*;
+
+use crate::layout_config::ClickthroughMode;
+
+use super::snapping::Bounds;
+
+/// Which parts of an overlay window catch the mouse
+pub struct InputShape {
+    window: gtk::Window,
+    is_clickthrough: bool,
+    mode: ClickthroughMode,
+    /// The visible parts of the page in the opaque pixels mode
+    opaque_region: RefCell<Option<cairo::Region>>,
+    /// The rectangles the page declared as interactive, in window coordinates
+    interactive_regions: RefCell<Vec<Bounds>>,
+    is_editing: Cell<bool>,
+    /// The interaction modifiers are held over the overlay
+    is_interacting: Cell<bool>,
+    /// The overlay is hidden while idle, but not withdrawn
+    is_hidden: Cell<bool>
+}
+
+impl InputShape {
+    pub fn new(window: &gtk::Window, is_clickthrough: bool, mode: ClickthroughMode) -> Self {
+        let input_shape = Self {
+            window: window.clone(),
+            is_clickthrough,
+            mode,
+            opaque_region: RefCell::new(None),
+            interactive_regions: RefCell::new(Vec::new()),
+            is_editing: Cell::new(false),
+            is_interacting: Cell::new(false),
+            is_hidden: Cell::new(false)
+        };
+        input_shape.apply();
+
+        input_shape
+    }
+
+    /// The overlay must catch the mouse while it is edited, even when clickthrough
+    pub fn set_editing(&self, is_editing: bool) {
+        self.is_editing.set(is_editing);
+        self.apply();
+    }
+
+    pub fn set_interacting(&self, is_interacting: bool) {
+        if self.is_interacting.replace(is_interacting) != is_interacting {
+            self.apply();
+        }
+    }
+
+    pub fn set_hidden(&self, is_hidden: bool) {
+        if self.is_hidden.replace(is_hidden) != is_hidden {
+            self.apply();
+        }
+    }
+
+    pub fn set_interactive_regions(&self, regions: Vec<Bounds>) {
+        if *self.interactive_regions.borrow() == regions {
+            return;
+        }
+
+        *self.interactive_regions.borrow_mut() = regions;
+        self.apply();
+    }
+
+    pub fn set_opaque_region(&self, region: cairo::Region) {
+        if self.opaque_region.borrow().as_ref() == Some(&region) {
+            return;
+        }
+
+        *self.opaque_region.borrow_mut() = Some(region);
+        self.apply();
+    }
+
+    fn apply(&self) {
+        // A hidden overlay lets everything through, whatever its mode
+        if self.is_hidden.get() {
+            self.window.input_shape_combine_region(Some(&cairo::Region::create()));
+            return;
+        }
+
+        if !self.is_clickthrough || self.is_editing.get() || self.is_interacting.get() {
+            self.window.input_shape_combine_region(None);
+            return;
+        }
+
+        // Everything passes through, except the visible parts of the page and the interactive regions
+        let region = cairo::Region::create();
+        if let (ClickthroughMode::OpaquePixels, Some(opaque_region)) = (self.mode, self.opaque_region.borrow().as_ref()) {
+            let _ = region.union(opaque_region);
+        }
+        for bounds in self.interactive_regions.borrow().iter() {
+            let _ = region.union_rectangle(&cairo::RectangleInt::new(bounds.x, bounds.y, bounds.width, bounds.height));
+        }
+        self.window.input_shape_combine_region(Some(&region));
+    }
+}
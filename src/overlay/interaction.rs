@@ -0,0 +1,99 @@
+use std::rc::Rc;
+use std::time::Duration;
+
+use glib::SourceId;
+use gtk::prelude::*;
+
+use super::input_shape::InputShape;
+
+/// The modifiers are pressed in the game, they are polled as the overlays never get the keyboard
+const POLLING_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Parse modifiers written as `ctrl+alt`, `none` or an empty text disabling the interaction
+pub fn parse_modifiers(modifiers: &str) -> Option<gdk::ModifierType> {
+    let mut modifier_type = gdk::ModifierType::empty();
+
+    for name in modifiers.split('+').map(|name| name.trim().to_lowercase()).filter(|name| !name.is_empty()) {
+        modifier_type |= match name.as_str() {
+            "ctrl" | "control" => gdk::ModifierType::CONTROL_MASK,
+            "alt" => gdk::ModifierType::MOD1_MASK,
+            "shift" => gdk::ModifierType::SHIFT_MASK,
+            "super" | "meta" => gdk::ModifierType::MOD4_MASK,
+            "none" => return None,
+            _ => {
+                warn!("Unknown modifier {}, the interaction with clickthrough overlays is disabled", name);
+                return None;
+            }
+        };
+    }
+
+    (!modifier_type.is_empty()).then_some(modifier_type)
+}
+
+/// Makes a clickthrough overlay interactive while the modifiers are held with the pointer over it
+pub struct InteractionHold {
+    timer: SourceId
+}
+
+impl InteractionHold {
+    pub fn start(window: &gtk::Window, input_shape: Rc<InputShape>, modifiers: gdk::ModifierType) -> Self {
+        let display = WidgetExt::display(window);
+        let keymap = gdk::Keymap::for_display(&display);
+        let pointer = display.default_seat().and_then(|seat| seat.pointer());
+
+        let window = window.clone();
+        let timer = glib::timeout_add_local(POLLING_INTERVAL, move || {
+            let modifier_state = keymap
+                .as_ref()
+                .map(|keymap| gdk::ModifierType::from_bits_truncate(keymap.modifier_state()))
+                .unwrap_or_else(gdk::ModifierType::empty);
+            let is_held = modifier_state.contains(modifiers);
+
+            input_shape.set_interacting(is_held && is_under_pointer(&window, pointer.as_ref()));
+            glib::Continue(true)
+        });
+
+        Self { timer }
+    }
+
+    pub fn stop(self) {
+        self.timer.remove();
+    }
+}
+
+pub(super) fn is_under_pointer(window: &gtk::Window, pointer: Option<&gdk::Device>) -> bool {
+    let (Some(gdk_window), Some(pointer)) = (window.window(), pointer) else {
+        return false;
+    };
+    if !window.is_visible() {
+        return false;
+    }
+
+    let (_, pointer_x, pointer_y) = pointer.position();
+    let (_, x, y) = gdk_window.origin();
+
+    pointer_x >= x && pointer_x < x + gdk_window.width() && pointer_y >= y && pointer_y < y + gdk_window.height()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_the_modifiers() {
+        assert_eq!(parse_modifiers("ctrl+alt"), Some(gdk::ModifierType::CONTROL_MASK | gdk::ModifierType::MOD1_MASK));
+        assert_eq!(parse_modifiers(" Shift + SUPER "), Some(gdk::ModifierType::SHIFT_MASK | gdk::ModifierType::MOD4_MASK));
+        assert_eq!(parse_modifiers("control+meta"), Some(gdk::ModifierType::CONTROL_MASK | gdk::ModifierType::MOD4_MASK));
+        // Empty parts are ignored
+        assert_eq!(parse_modifiers("alt++"), Some(gdk::ModifierType::MOD1_MASK));
+    }
+
+    #[test]
+    fn empty_or_unknown_modifiers_disable_the_interaction() {
+        assert_eq!(parse_modifiers(""), None);
+        assert_eq!(parse_modifiers(" + "), None);
+        assert_eq!(parse_modifiers("none"), None);
+        assert_eq!(parse_modifiers("ctrl+none"), None);
+        assert_eq!(parse_modifiers("ctrl+hyper"), None);
+    }
+}
@@ -0,0 +1,62 @@
+// Reports the interactive regions of the page to xiv-overlay, so a clickthrough overlay
+// still catches the mouse over them. Elements are marked with the data-overlay-interactive
+// attribute, or regions are declared with window.xivOverlay.setInteractiveRegions.
+(() => {
+    const ATTRIBUTE = 'data-overlay-interactive';
+
+    let declaredRegions = [];
+    let lastMessage = null;
+    let isScheduled = false;
+
+    function sendRegions() {
+        isScheduled = false;
+
+        const regions = Array.from(document.querySelectorAll(`[${ATTRIBUTE}]`))
+            .map((element) => element.getBoundingClientRect())
+            .filter((rect) => rect.width > 0 && rect.height > 0)
+            .map((rect) => [rect.left, rect.top, rect.width, rect.height])
+            .concat(declaredRegions);
+
+        const message = JSON.stringify(regions);
+        if (message !== lastMessage) {
+            lastMessage = message;
+            window.webkit.messageHandlers.xivOverlayRegions.postMessage(message);
+        }
+    }
+
+    function scheduleUpdate() {
+        if (!isScheduled) {
+            isScheduled = true;
+            requestAnimationFrame(sendRegions);
+        }
+    }
+
+    window.xivOverlay = Object.assign(window.xivOverlay || {}, {
+        setInteractiveRegions(regions) {
+            declaredRegions = regions.map(({ x, y, width, height }) => [x, y, width, height]);
+            scheduleUpdate();
+        }
+    });
+
+    function observePage() {
+        new MutationObserver(scheduleUpdate).observe(document.documentElement, {
+            attributes: true,
+            childList: true,
+            characterData: true,
+            subtree: true
+        });
+        scheduleUpdate();
+    }
+
+    window.addEventListener('resize', scheduleUpdate);
+    document.addEventListener('scroll', scheduleUpdate, true);
+    document.addEventListener('transitionend', scheduleUpdate, true);
+    document.addEventListener('animationend', scheduleUpdate, true);
+
+    // The script runs before the document is parsed, the root element may not exist yet
+    if (document.documentElement) {
+        observePage();
+    } else {
+        document.addEventListener('DOMContentLoaded', observePage, { once: true });
+    }
+})();
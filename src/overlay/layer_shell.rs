@@ -0,0 +1,207 @@
+use std::sync::OnceLock;
+
+use gtk::prelude::*;
+
+use crate::app_config::AppConfig;
+use crate::layout_config::LayoutConfig;
+
+use super::placement::{self, Alignment};
+
+/// Places the overlays on wlr-layer-shell compositors, through gtk-layer-shell
+pub struct LayerShell {
+    _private: ()
+}
+
+/// The layer shell, when running on a Wayland compositor supporting it
+pub fn layer_shell() -> Option<&'static LayerShell> {
+    static LAYER_SHELL: OnceLock<Option<LayerShell>> = OnceLock::new();
+
+    LAYER_SHELL.get_or_init(LayerShell::load).as_ref()
+}
+
+impl LayerShell {
+    fn load() -> Option<LayerShell> {
+        if !AppConfig::default().use_layer_shell() {
+            info!("The layer shell is disabled, the overlays are regular windows");
+            return None;
+        }
+
+        let display = gdk::Display::default()?;
+        if display.type_().name() != "GdkWaylandDisplay" {
+            return None;
+        }
+
+        if !backend::is_available() {
+            warn!("Built without the layer-shell feature, the overlays can not be placed on Wayland");
+            return None;
+        }
+        if !backend::is_supported() {
+            warn!("The compositor does not support the layer shell protocol, the overlays are regular windows");
+            return None;
+        }
+        info!("Using the layer shell to display the overlays");
+
+        Some(LayerShell { _private: () })
+    }
+
+    /// Turn the window into a layer surface above every other window, must be called before it is shown
+    pub fn init_window(&self, window: &gtk::Window) {
+        backend::init_window(window);
+    }
+
+    /// Anchor the window to the edges matching the anchor of the overlay, the offsets becoming margins
+    pub fn place_window(&self, window: &gtk::Window, config: &LayoutConfig) {
+        let display = WidgetExt::display(window);
+        let geometry = placement::overlay_geometry(config, &display, None);
+        let Some(monitor) = placement::monitor_at(&display, &geometry) else {
+            return;
+        };
+        let Some(gdk_monitor) = display.monitor(monitor.index) else {
+            return;
+        };
+        let area = monitor.geometry;
+
+        let (horizontal, vertical) = placement::anchor_alignment(config.anchor());
+        let (left, right) = edge_margins(horizontal, geometry.x() - area.x(), area.x() + area.width() - geometry.x() - geometry.width());
+        let (top, bottom) = edge_margins(vertical, geometry.y() - area.y(), area.y() + area.height() - geometry.y() - geometry.height());
+        backend::anchor_window(window, &gdk_monitor, [left, right, top, bottom]);
+
+        window.set_size_request(geometry.width(), geometry.height());
+        window.resize(geometry.width(), geometry.height());
+    }
+
+    /// Where the window is, a layer surface does not know its position
+    pub fn window_position(&self, window: &gtk::Window, config: &LayoutConfig) -> (i32, i32) {
+        let geometry = placement::overlay_geometry(config, &WidgetExt::display(window), None);
+        (geometry.x(), geometry.y())
+    }
+
+    /// Layer surfaces ignore `move_`, the overlay is moved to the position then anchored again with new margins
+    pub fn move_window(&self, window: &gtk::Window, config: &mut LayoutConfig, x: i32, y: i32) {
+        let (width, height) = window.size();
+        placement::move_config_to(config, &gdk::Rectangle::new(x, y, width, height), &WidgetExt::display(window), None);
+        self.place_window(window, config);
+    }
+}
+
+/// The margins of the start and end edges of one axis, the edges without a margin are not anchored.
+/// A centred overlay is only left to the compositor when it is not offset.
+fn edge_margins(alignment: Alignment, start_margin: i32, end_margin: i32) -> (Option<i32>, Option<i32>) {
+    match alignment {
+        Alignment::End => (None, Some(end_margin)),
+        Alignment::Center if (start_margin - end_margin).abs() <= 1 => (None, None),
+        _ => (Some(start_margin), None)
+    }
+}
+
+#[cfg(feature = "layer-shell")]
+mod backend {
+    use gtk_layer_shell::{Edge, Layer};
+
+    const NAMESPACE: &str = "xiv-overlay";
+
+    pub fn is_available() -> bool {
+        true
+    }
+
+    pub fn is_supported() -> bool {
+        gtk_layer_shell::is_supported()
+    }
+
+    pub fn init_window(window: &gtk::Window) {
+        gtk_layer_shell::init_for_window(window);
+        gtk_layer_shell::set_namespace(window, Some(NAMESPACE));
+        gtk_layer_shell::set_layer(window, Layer::Overlay);
+        // Place the overlays relative to the monitor edges, ignoring the panels
+        gtk_layer_shell::set_exclusive_zone(window, -1);
+        gtk_layer_shell::set_keyboard_interactivity(window, false);
+    }
+
+    /// The margins of the left, right, top and bottom edges, `None` for the edges not anchored
+    pub fn anchor_window(window: &gtk::Window, monitor: &gdk::Monitor, margins: [Option<i32>; 4]) {
+        gtk_layer_shell::set_monitor(window, monitor);
+        for (edge, margin) in [Edge::Left, Edge::Right, Edge::Top, Edge::Bottom].into_iter().zip(margins) {
+            gtk_layer_shell::set_anchor(window, edge, margin.is_some());
+            gtk_layer_shell::set_margin(window, edge, margin.unwrap_or(0));
+        }
+    }
+}
+
+/// Built without gtk-layer-shell, the overlays are regular windows on Wayland
+#[cfg(not(feature = "layer-shell"))]
+mod backend {
+    pub fn is_available() -> bool {
+        false
+    }
+
+    pub fn is_supported() -> bool {
+        false
+    }
+
+    pub fn init_window(_window: &gtk::Window) {}
+
+    pub fn anchor_window(_window: &gtk::Window, _monitor: &gdk::Monitor, _margins: [Option<i32>; 4]) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn an_overlay_at_the_end_is_anchored_to_the_end_edge() {
+        assert_eq!(edge_margins(Alignment::End, 1500, 20), (None, Some(20)));
+    }
+
+    #[test]
+    fn an_overlay_at_the_start_is_anchored_to_the_start_edge() {
+        assert_eq!(edge_margins(Alignment::Start, 20, 1500), (Some(20), None));
+    }
+
+    #[test]
+    fn a_centred_overlay_without_offset_is_not_anchored() {
+        assert_eq!(edge_margins(Alignment::Center, 810, 810), (None, None));
+        // The odd pixel of the centring is not an offset
+        assert_eq!(edge_margins(Alignment::Center, 810, 811), (None, None));
+    }
+
+    #[test]
+    fn an_offset_centred_overlay_is_anchored_to_the_start_edge() {
+        assert_eq!(edge_margins(Alignment::Center, 860, 760), (Some(860), None));
+    }
+
+    /// Run inside a compositor with the layer shell, e.g. `WLR_BACKENDS=headless sway`, with
+    /// `GDK_BACKEND=wayland cargo test --features layer-shell -- --ignored layer_shell`
+    #[cfg(feature = "layer-shell")]
+    #[test]
+    #[ignore = "needs a Wayland compositor supporting the layer shell, like a headless sway"]
+    fn places_an_overlay_on_a_headless_compositor() {
+        use crate::layout_config::Anchor;
+
+        gtk::init().expect("Could not connect to the compositor");
+        let layer_shell = layer_shell().expect("The compositor does not support the layer shell");
+
+        let mut config = LayoutConfig::default();
+        config.set_anchor(Anchor::BottomRight);
+        config.set_x(30);
+        config.set_y(40);
+        config.set_width(300);
+        config.set_height(200);
+
+        let window = gtk::Window::new(gtk::WindowType::Toplevel);
+        layer_shell.init_window(&window);
+        layer_shell.place_window(&window, &config);
+        window.show_all();
+        while gtk::events_pending() {
+            gtk::main_iteration();
+        }
+
+        assert!(gtk_layer_shell::is_layer_window(&window));
+        assert_eq!(window.size(), (300, 200));
+
+        // Moving the window changes the offsets from the bottom right corner
+        let (x, y) = layer_shell.window_position(&window, &config);
+        layer_shell.move_window(&window, &mut config, x - 50, y - 60);
+        assert_eq!((config.x(), config.y()), (80, 100));
+        assert_eq!(layer_shell.window_position(&window, &config), (x - 50, y - 60));
+    }
+}
@@ -0,0 +1,364 @@
+pub mod alpha_shape;
+pub mod edit_mode;
+pub mod game_window;
+pub mod idle_fade;
+pub mod input_shape;
+pub mod interaction;
+pub mod layer_shell;
+pub mod page_bridge;
+pub mod placement;
+pub mod snapping;
+pub mod visibility;
+
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::time::Duration;
+
+use crate::app::AppAction;
+use crate::app_config::AppConfig;
+use crate::layout_config::{ClickthroughMode, LayoutConfig};
+use crate::relay::game_state::GameState;
+
+use self::edit_mode::{EditContext, EditMode};
+use self::game_window::{FocusedWindow, OVERLAY_ROLE};
+use self::alpha_shape::AlphaShape;
+use self::idle_fade::IdleFade;
+use self::input_shape::InputShape;
+use self::interaction::InteractionHold;
+use self::layer_shell::layer_shell;
+use self::snapping::Bounds;
+use self::visibility::VisibilityState;
+
+use async_channel::Sender;
+use glib::{Receiver, SourceId};
+use gtk::prelude::{ContainerExt, ObjectExt, OverlayExt, LabelExt, StyleContextExt, WidgetExtManual};
+use gtk::traits::GtkWindowExt;
+use gtk::{Inhibit, Window, WindowType, traits::WidgetExt};
+use gdk::RGBA;
+use webkit2gtk::{
+    traits::{SettingsExt, WebInspectorExt, WebViewExt},
+    LoadEvent, NetworkError, UserContentManager, WebContext, WebView,
+};
+
+const MIN_RETRY_DELAY: Duration = Duration::from_secs(1);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// Sent by the app to a displayed overlay
+pub enum OverlayCommand {
+    Close,
+    Reload,
+    /// Open the web inspector of the page
+    ShowInspector,
+    /// Enter or leave the layout edition
+    SetEditMode(Option<EditContext>),
+    /// The client area of the game window moved, resized, appeared or disappeared
+    SetGameArea(Option<Bounds>),
+    /// The focused window changed, unknown when it can not be tracked
+    SetFocus(Option<FocusedWindow>),
+    SetGameState(GameState),
+    /// The position or size of the overlay was changed from the manager
+    SetPlacement(LayoutConfig)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OverlayStatus {
+    Loading,
+    Running,
+    /// Running, but the relay lost the connection to the upstream server. Never reported by the overlay itself
+    Disconnected,
+    Failed(String)
+}
+
+/// Reloads the overlay with an exponential backoff when the page or the web process fails
+struct LoadRetry {
+    overlay_name: String,
+    url: String,
+    webview: WebView,
+    error_label: gtk::Label,
+    event_sender: Sender<AppAction>,
+    delay: Cell<Duration>,
+    timer: RefCell<Option<SourceId>>,
+    has_failed: Cell<bool>
+}
+
+impl LoadRetry {
+    fn on_load_changed(&self, load_event: LoadEvent) {
+        match load_event {
+            LoadEvent::Started => self.report_status(OverlayStatus::Loading),
+            // A failed load is finished as well
+            LoadEvent::Finished if !self.has_failed.get() => {
+                self.delay.set(MIN_RETRY_DELAY);
+                self.error_label.hide();
+                self.report_status(OverlayStatus::Running);
+            },
+            _ => {}
+        }
+    }
+
+    fn schedule_retry(self: &Rc<Self>, reason: String) {
+        self.has_failed.set(true);
+        if self.timer.borrow().is_some() {
+            return;
+        }
+
+        let delay = self.delay.get();
+        self.delay.set((delay * 2).min(MAX_RETRY_DELAY));
+
+        warn!("Overlay {} failed: {}, retrying in {:?}", self.overlay_name, reason, delay);
+        self.error_label.set_text(&format!("{}\nRetrying in {}s", reason, delay.as_secs()));
+        self.error_label.show();
+        self.report_status(OverlayStatus::Failed(reason));
+
+        let retry = self.clone();
+        *self.timer.borrow_mut() = Some(glib::timeout_add_local_once(delay, move || {
+            retry.timer.borrow_mut().take();
+            retry.has_failed.set(false);
+            retry.webview.load_uri(&retry.url);
+        }));
+    }
+
+    fn cancel(&self) {
+        if let Some(timer) = self.timer.borrow_mut().take() {
+            timer.remove();
+        }
+    }
+
+    fn report_status(&self, status: OverlayStatus) {
+        let event_sender = self.event_sender.clone();
+        let overlay_name = self.overlay_name.clone();
+        glib::MainContext::default().spawn_local(async move {
+            let _ = event_sender.send(AppAction::OverlayStatusChanged(overlay_name, status)).await;
+        });
+    }
+}
+
+fn set_visual(window: &gtk::Window, _screen: Option<&gdk::Screen>) {
+    if let Some(screen) = GtkWindowExt::screen(window) {
+        if let Some(ref visual) = screen.rgba_visual() {
+            window.set_visual(Some(visual)); // crucial for transparency
+        }
+    }
+}
+
+fn place_window(window: &Window, config: &LayoutConfig, game_area: Option<Bounds>) {
+    // Layer surfaces can not be moved, they are anchored to the edges of their monitor
+    if let Some(layer_shell) = layer_shell() {
+        layer_shell.place_window(window, config);
+        return;
+    }
+
+    let game_area = game_area.map(Bounds::to_rectangle);
+    let geometry = placement::overlay_geometry(config, &WidgetExt::display(window), game_area.as_ref());
+
+    window.set_size_request(geometry.width(), geometry.height());
+    window.resize(geometry.width(), geometry.height());
+    window.move_(geometry.x(), geometry.y());
+}
+
+pub fn show_overlay(
+    config: &LayoutConfig,
+    game_area: Option<Bounds>,
+    visibility_state: VisibilityState,
+    command_receiver: Receiver<OverlayCommand>,
+    event_sender: Sender<AppAction>
+) {
+    let window = Window::new(WindowType::Toplevel);
+    set_visual(&window, None);
+
+    window.set_app_paintable(true);
+    window.set_role(OVERLAY_ROLE);
+    window.set_decorated(config.is_decoraded());
+    match layer_shell() {
+        Some(layer_shell) => layer_shell.init_window(&window),
+        None => window.set_keep_above(true)
+    }
+    place_window(&window, config, game_area);
+
+    // The config and the game area are shared with the edit mode, which moves the overlay
+    let config = Rc::new(RefCell::new(config.clone()));
+    let game_area = Rc::new(Cell::new(game_area));
+
+    // Follow the monitor of the overlay when monitors are plugged, unplugged, rearranged or resized
+    let screen = GtkWindowExt::screen(&window).expect("Could not fetch the gdk screen");
+    let (config_cloned, game_area_cloned) = (config.clone(), game_area.clone());
+    let monitors_handler_id = screen.connect_monitors_changed(glib::clone!(@weak window => move |_| {
+        place_window(&window, &config_cloned.borrow(), game_area_cloned.get());
+    }));
+    let (config_cloned, game_area_cloned) = (config.clone(), game_area.clone());
+    let size_handler_id = screen.connect_size_changed(glib::clone!(@weak window => move |_| {
+        place_window(&window, &config_cloned.borrow(), game_area_cloned.get());
+    }));
+    let mut screen_handler_ids = vec![monitors_handler_id, size_handler_id];
+
+    window.connect_screen_changed(set_visual);
+    window.connect_draw(|_window, ctx| {
+        ctx.set_source_rgba(1.0, 0.0, 0.0, 0.0);
+        ctx.set_operator(cairo::Operator::Screen);
+        let _ = ctx.paint();
+        Inhibit(false)
+    });
+
+    let input_shape = Rc::new(InputShape::new(
+        &window,
+        config.borrow().is_clickthrough(),
+        config.borrow().clickthrough_mode()
+    ));
+
+    let idle_fade = IdleFade::start(&window, input_shape.clone(), &config.borrow());
+
+    let context = WebContext::default().unwrap();
+    let content_manager = UserContentManager::new();
+    page_bridge::connect_interactive_regions(&content_manager, input_shape.clone(), config.borrow().zoom());
+    page_bridge::add_user_css(&content_manager, &config.borrow().user_css());
+    if let Some(idle_fade) = idle_fade.clone() {
+        page_bridge::connect_data_activity(&content_manager, move || idle_fade.on_activity());
+    }
+
+    let webview = WebView::builder()
+        .web_context(&context)
+        .user_content_manager(&content_manager)
+        .build();
+    webview.set_background_color(&RGBA::new(0.0, 0.0, 0.0, 0.0));
+    webview.set_zoom_level(config.borrow().zoom());
+
+    // Moving the pointer over an idle overlay brings it back
+    if let Some(idle_fade) = idle_fade.clone() {
+        webview.add_events(gdk::EventMask::POINTER_MOTION_MASK);
+        webview.connect_motion_notify_event(move |_, _| {
+            idle_fade.on_activity();
+            Inhibit(false)
+        });
+    }
+
+    // The regions of the previous page do not apply to the new one
+    let input_shape_cloned = input_shape.clone();
+    webview.connect_load_changed(move |_, load_event| {
+        if load_event == LoadEvent::Committed {
+            input_shape_cloned.set_interactive_regions(Vec::new());
+        }
+    });
+
+    let error_label = gtk::Label::new(None);
+    error_label.style_context().add_class("overlay-error");
+    error_label.set_halign(gtk::Align::Start);
+    error_label.set_valign(gtk::Align::Start);
+    error_label.set_line_wrap(true);
+    error_label.set_no_show_all(true);
+
+    let alpha_shape = (config.borrow().is_clickthrough() && config.borrow().clickthrough_mode() == ClickthroughMode::OpaquePixels)
+        .then(|| AlphaShape::start(&webview, input_shape.clone(), config.borrow().alpha_sampling_interval()));
+    let mut interaction_hold = AppConfig::default()
+        .interaction_modifiers()
+        .filter(|_| config.borrow().is_clickthrough())
+        .map(|modifiers| InteractionHold::start(&window, input_shape.clone(), modifiers));
+    let edit_mode = EditMode::new(&window, config.clone(), game_area.clone(), input_shape, event_sender.clone());
+
+    let container = gtk::Overlay::new();
+    container.add(&webview);
+    container.add_overlay(&error_label);
+    container.add_overlay(edit_mode.drag_area());
+    window.add(&container);
+
+    let shared_config = config;
+    let config = shared_config.borrow().clone();
+    let load_retry = Rc::new(LoadRetry {
+        overlay_name: config.name(),
+        url: config.url(),
+        webview: webview.clone(),
+        error_label,
+        event_sender,
+        delay: Cell::new(MIN_RETRY_DELAY),
+        timer: RefCell::new(None),
+        has_failed: Cell::new(false)
+    });
+
+    let retry = load_retry.clone();
+    webview.connect_load_changed(move |_, load_event| retry.on_load_changed(load_event));
+
+    let retry = load_retry.clone();
+    webview.connect_load_failed(move |_, _, _, error| {
+        // Cancelled loads are replaced by another one, they are not failures
+        if !error.matches(NetworkError::Cancelled) {
+            retry.schedule_retry(error.to_string());
+        }
+        // Do not display the error page of webkit, the overlay must stay transparent
+        true
+    });
+
+    let retry = load_retry.clone();
+    webview.connect_web_process_terminated(move |_, reason| {
+        retry.schedule_retry(format!("The web process terminated ({:?})", reason));
+    });
+
+    webview.load_uri(&config.url());
+
+    let settings = WebViewExt::settings(&webview).unwrap();
+    settings.set_enable_developer_extras(true);
+    
+    // The window is only hidden while the rule does not match, the webview keeps running
+    container.show_all();
+    if visibility_state.allows(&config) {
+        window.show();
+    }
+
+    let mut visibility_state = visibility_state;
+    command_receiver.attach(None, move |command| {
+        match command {
+            OverlayCommand::Close => {
+                load_retry.cancel();
+                if let Some(alpha_shape) = &alpha_shape {
+                    alpha_shape.stop();
+                }
+                if let Some(interaction_hold) = interaction_hold.take() {
+                    interaction_hold.stop();
+                }
+                if let Some(idle_fade) = &idle_fade {
+                    idle_fade.stop();
+                }
+                edit_mode.close();
+                for handler_id in screen_handler_ids.drain(..) {
+                    screen.disconnect(handler_id);
+                }
+                window.close();
+                return glib::Continue(false);
+            },
+            OverlayCommand::Reload => webview.reload(),
+            OverlayCommand::ShowInspector => {
+                if let Some(inspector) = webview.inspector() {
+                    inspector.show();
+                }
+            },
+            OverlayCommand::SetEditMode(context) => {
+                edit_mode.set_context(context);
+                if let Some(idle_fade) = &idle_fade {
+                    idle_fade.set_suspended(edit_mode.is_editing());
+                }
+            },
+            OverlayCommand::SetGameArea(area) => {
+                game_area.set(area);
+                let config = shared_config.borrow();
+                if config.follows_game_window() {
+                    place_window(&window, &config, area);
+                }
+            },
+            OverlayCommand::SetFocus(focus) => visibility_state.focus = focus,
+            OverlayCommand::SetGameState(game_state) => visibility_state.game_state = game_state,
+            OverlayCommand::SetPlacement(config) => {
+                *shared_config.borrow_mut() = config;
+                place_window(&window, &shared_config.borrow(), game_area.get());
+            }
+        }
+
+        // An edited overlay stays visible to be moved around
+        let should_show = edit_mode.is_editing() || visibility_state.allows(&shared_config.borrow());
+        if should_show && !window.is_visible() {
+            window.show();
+            // The window manager may have placed it elsewhere while it was hidden
+            place_window(&window, &shared_config.borrow(), game_area.get());
+        } else if !should_show && window.is_visible() {
+            window.hide();
+        }
+
+        glib::Continue(true)
+    });
+}
\ No newline at end of file
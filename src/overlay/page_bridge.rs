@@ -0,0 +1,124 @@
+use std::rc::Rc;
+
+use javascriptcore::ValueExt;
+use webkit2gtk::{
+    traits::UserContentManagerExt,
+    UserContentInjectedFrames, UserContentManager, UserScript, UserScriptInjectionTime, UserStyleLevel, UserStyleSheet,
+};
+
+use super::input_shape::InputShape;
+use super::snapping::Bounds;
+
+const REGIONS_HANDLER: &str = "xivOverlayRegions";
+const REGIONS_SCRIPT: &str = include_str!("./interactive_regions.js");
+const ACTIVITY_HANDLER: &str = "xivOverlayActivity";
+const ACTIVITY_SCRIPT: &str = include_str!("./data_activity.js");
+
+/// Let the page declare the regions of a clickthrough overlay catching the mouse, `zoom` is the zoom level of the page
+pub fn connect_interactive_regions(content_manager: &UserContentManager, input_shape: Rc<InputShape>, zoom: f64) {
+    content_manager.add_script(&UserScript::new(
+        REGIONS_SCRIPT,
+        UserContentInjectedFrames::TopFrame,
+        UserScriptInjectionTime::Start,
+        &[],
+        &[]
+    ));
+
+    if !content_manager.register_script_message_handler(REGIONS_HANDLER) {
+        warn!("Could not register the {} message handler", REGIONS_HANDLER);
+        return;
+    }
+
+    content_manager.connect_script_message_received(Some(REGIONS_HANDLER), move |_, result| {
+        let Some(message) = result.js_value().map(|value| value.to_str()) else {
+            return;
+        };
+
+        match parse_regions(&message, zoom) {
+            Some(regions) => input_shape.set_interactive_regions(regions),
+            None => warn!("Invalid interactive regions sent by the page: {}", message)
+        }
+    });
+}
+
+/// Call `on_activity` while the page receives data from the websockets it opened
+pub fn connect_data_activity(content_manager: &UserContentManager, on_activity: impl Fn() + 'static) {
+    content_manager.add_script(&UserScript::new(
+        ACTIVITY_SCRIPT,
+        UserContentInjectedFrames::TopFrame,
+        UserScriptInjectionTime::Start,
+        &[],
+        &[]
+    ));
+
+    if !content_manager.register_script_message_handler(ACTIVITY_HANDLER) {
+        warn!("Could not register the {} message handler", ACTIVITY_HANDLER);
+        return;
+    }
+
+    content_manager.connect_script_message_received(Some(ACTIVITY_HANDLER), move |_, _| on_activity());
+}
+
+/// Add the style sheet of the overlay over the styles of the page, nothing for an empty one
+pub fn add_user_css(content_manager: &UserContentManager, user_css: &str) {
+    if user_css.trim().is_empty() {
+        return;
+    }
+
+    content_manager.add_style_sheet(&UserStyleSheet::new(
+        user_css,
+        UserContentInjectedFrames::AllFrames,
+        UserStyleLevel::User,
+        &[],
+        &[]
+    ));
+}
+
+/// The regions are sent as a JSON array of [x, y, width, height] in CSS pixels, scaled by the zoom into window pixels
+fn parse_regions(message: &str, zoom: f64) -> Option<Vec<Bounds>> {
+    let regions: Vec<[f64; 4]> = serde_json::from_str(message).ok()?;
+
+    let bounds = regions
+        .iter()
+        .map(|[x, y, width, height]| Bounds {
+            x: (x * zoom).floor() as i32,
+            y: (y * zoom).floor() as i32,
+            width: (width * zoom).ceil() as i32,
+            height: (height * zoom).ceil() as i32
+        })
+        .collect();
+
+    Some(bounds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_the_regions_rounding_them_outwards() {
+        let regions = parse_regions("[[10, 20, 100, 50], [0.5, 1.5, 10.2, 20.7]]", 1.0).unwrap();
+        assert_eq!(regions, vec![
+            Bounds { x: 10, y: 20, width: 100, height: 50 },
+            Bounds { x: 0, y: 1, width: 11, height: 21 }
+        ]);
+        assert_eq!(parse_regions("[]", 1.0), Some(Vec::new()));
+    }
+
+    #[test]
+    fn scales_the_regions_by_the_zoom_of_the_page() {
+        let regions = parse_regions("[[10, 20, 100, 50], [0.5, 1.5, 10.2, 20.7]]", 1.5).unwrap();
+        assert_eq!(regions, vec![
+            Bounds { x: 15, y: 30, width: 150, height: 75 },
+            Bounds { x: 0, y: 2, width: 16, height: 32 }
+        ]);
+    }
+
+    #[test]
+    fn rejects_the_malformed_regions() {
+        assert_eq!(parse_regions("[[10, 20, 100", 1.0), None);
+        assert_eq!(parse_regions("[[10, 20, 100]]", 1.0), None);
+        assert_eq!(parse_regions(r#"[{"x": 10, "y": 20, "width": 100, "height": 50}]"#, 1.0), None);
+        assert_eq!(parse_regions(r#"[["10", 20, 100, 50]]"#, 1.0), None);
+    }
+}
@@ -0,0 +1,349 @@
+use crate::layout_config::{Anchor, LayoutConfig, SizeUnit};
+
+use glib::prelude::*;
+
+#[derive(Debug, Clone)]
+pub struct MonitorInfo {
+    /// Position of the monitor in the display
+    pub index: i32,
+    /// Connector like `DP-1`, manufacturer and model when the connector is unknown
+    pub identifier: String,
+    pub geometry: gdk::Rectangle,
+    pub is_primary: bool
+}
+
+/// The connected monitors, identical monitors are told apart by their order
+pub fn monitors(display: &gdk::Display) -> Vec<MonitorInfo> {
+    // The model of a monitor on X11 is the name of its RandR output
+    let is_x11 = display.type_().name() == "GdkX11Display";
+
+    let connected = (0..display.n_monitors())
+        .filter_map(|index| display.monitor(index).map(|monitor| (index, monitor)))
+        .collect::<Vec<_>>();
+    let names = connected
+        .iter()
+        .map(|(index, monitor)| {
+            let (manufacturer, model) = (monitor.manufacturer(), monitor.model());
+            let connector = model.as_deref().filter(|_| is_x11);
+            monitor_name(connector, manufacturer.as_deref(), model.as_deref(), *index)
+        })
+        .collect();
+
+    unique_identifiers(names)
+        .into_iter()
+        .zip(connected)
+        .map(|(identifier, (index, monitor))| MonitorInfo {
+            index,
+            identifier,
+            geometry: monitor.geometry(),
+            is_primary: monitor.is_primary()
+        })
+        .collect()
+}
+
+/// The connector of the monitor, or its manufacturer and model when it is unknown
+fn monitor_name(connector: Option<&str>, manufacturer: Option<&str>, model: Option<&str>, index: i32) -> String {
+    if let Some(connector) = connector.map(str::trim).filter(|connector| !connector.is_empty()) {
+        return connector.to_string();
+    }
+
+    let name = [manufacturer, model]
+        .iter()
+        .flatten()
+        .map(|part| part.trim())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<&str>>()
+        .join(" ");
+    if name.is_empty() { format!("Monitor {}", index + 1) } else { name }
+}
+
+/// Number the monitors sharing a name, the first one keeps it
+fn unique_identifiers(names: Vec<String>) -> Vec<String> {
+    let mut identifiers: Vec<String> = Vec::new();
+
+    for name in names {
+        let duplicates = identifiers
+            .iter()
+            .filter(|other| **other == name || other.starts_with(&format!("{} #", name)))
+            .count();
+        identifiers.push(if duplicates == 0 { name } else { format!("{} #{}", name, duplicates + 1) });
+    }
+
+    identifiers
+}
+
+fn primary_monitor(monitors: &[MonitorInfo]) -> Option<&MonitorInfo> {
+    monitors
+        .iter()
+        .find(|monitor| monitor.is_primary)
+        .or_else(|| monitors.first())
+}
+
+/// The absolute geometry of an overlay on the current monitors, or in the game window when it follows it
+pub fn overlay_geometry(config: &LayoutConfig, display: &gdk::Display, game_area: Option<&gdk::Rectangle>) -> gdk::Rectangle {
+    if let Some(game_area) = game_area.filter(|_| config.follows_game_window()) {
+        return geometry_in_area(config, game_area);
+    }
+
+    geometry_on_monitors(config, &monitors(display))
+}
+
+/// The absolute geometry of an overlay on the given monitors, a missing monitor is replaced by the primary one
+fn geometry_on_monitors(config: &LayoutConfig, monitors: &[MonitorInfo]) -> gdk::Rectangle {
+    let Some(primary) = primary_monitor(monitors) else {
+        return geometry_in_area(config, &gdk::Rectangle::new(0, 0, config.width(), config.height()));
+    };
+
+    match config.monitor() {
+        Some(identifier) => match monitors.iter().find(|monitor| monitor.identifier == identifier) {
+            Some(monitor) => geometry_in_area(config, &monitor.geometry),
+            None => {
+                warn!("The monitor {} of the overlay {} is not connected, using the primary monitor", identifier, config.name());
+                clamp_to(geometry_in_area(config, &primary.geometry), &primary.geometry)
+            }
+        },
+        // Relative to the whole screen, only moved when it ended up off-screen
+        None => {
+            let screen_area = screen_area(monitors, primary);
+            let rectangle = geometry_in_area(config, &screen_area);
+            let is_visible = monitors.iter().any(|monitor| monitor.geometry.intersect(&rectangle).is_some());
+            if is_visible {
+                rectangle
+            } else {
+                clamp_to(rectangle, &primary.geometry)
+            }
+        }
+    }
+}
+
+/// Update the anchor offsets of an overlay so it is placed at the given position.
+/// An overlay bound to a monitor follows the monitor it has been moved to.
+pub fn move_config_to(config: &mut LayoutConfig, rectangle: &gdk::Rectangle, display: &gdk::Display, game_area: Option<&gdk::Rectangle>) {
+    let monitors = monitors(display);
+
+    let game_area = game_area.filter(|_| config.follows_game_window());
+    let area = match (game_area, primary_monitor(&monitors)) {
+        (Some(game_area), _) => *game_area,
+        (None, None) => gdk::Rectangle::new(0, 0, rectangle.width(), rectangle.height()),
+        (None, Some(primary)) if config.monitor().is_some() => {
+            let monitor = find_monitor_at(&monitors, rectangle).unwrap_or(primary);
+            config.set_monitor(Some(monitor.identifier.clone()));
+            monitor.geometry
+        },
+        (None, Some(primary)) => screen_area(&monitors, primary)
+    };
+
+    let (horizontal, vertical) = anchor_alignment(config.anchor());
+    config.set_x(offset_of(horizontal, area.width(), rectangle.width(), rectangle.x() - area.x()));
+    config.set_y(offset_of(vertical, area.height(), rectangle.height(), rectangle.y() - area.y()));
+}
+
+/// Place an overlay at the given position and size, the size is kept in the unit of the overlay
+pub fn resize_config_to(config: &mut LayoutConfig, rectangle: &gdk::Rectangle, display: &gdk::Display, game_area: Option<&gdk::Rectangle>) {
+    move_config_to(config, rectangle, display, game_area);
+
+    let (width, height) = match config.size_unit() {
+        SizeUnit::Pixels => (rectangle.width(), rectangle.height()),
+        SizeUnit::Percent => {
+            let area = overlay_area(config, display, game_area);
+            (
+                (rectangle.width() as f64 * 100.0 / area.width().max(1) as f64).round() as i32,
+                (rectangle.height() as f64 * 100.0 / area.height().max(1) as f64).round() as i32
+            )
+        }
+    };
+    config.set_width(width);
+    config.set_height(height);
+}
+
+/// The area the offsets and the percent size of an overlay are relative to
+fn overlay_area(config: &LayoutConfig, display: &gdk::Display, game_area: Option<&gdk::Rectangle>) -> gdk::Rectangle {
+    if let Some(game_area) = game_area.filter(|_| config.follows_game_window()) {
+        return *game_area;
+    }
+
+    let monitors = monitors(display);
+    let Some(primary) = primary_monitor(&monitors) else {
+        return gdk::Rectangle::new(0, 0, config.width(), config.height());
+    };
+
+    match config.monitor() {
+        Some(identifier) => monitors
+            .iter()
+            .find(|monitor| monitor.identifier == identifier)
+            .unwrap_or(primary)
+            .geometry,
+        None => screen_area(&monitors, primary)
+    }
+}
+
+/// The monitor containing the centre of a rectangle, the primary monitor when it is off-screen
+pub fn monitor_at(display: &gdk::Display, rectangle: &gdk::Rectangle) -> Option<MonitorInfo> {
+    let monitors = monitors(display);
+    find_monitor_at(&monitors, rectangle)
+        .or_else(|| primary_monitor(&monitors))
+        .cloned()
+}
+
+fn find_monitor_at<'a>(monitors: &'a [MonitorInfo], rectangle: &gdk::Rectangle) -> Option<&'a MonitorInfo> {
+    let (center_x, center_y) = (rectangle.x() + rectangle.width() / 2, rectangle.y() + rectangle.height() / 2);
+
+    monitors.iter().find(|monitor| {
+        let geometry = monitor.geometry;
+        center_x >= geometry.x() && center_x < geometry.x() + geometry.width()
+            && center_y >= geometry.y() && center_y < geometry.y() + geometry.height()
+    })
+}
+
+/// The union of all the monitors
+fn screen_area(monitors: &[MonitorInfo], primary: &MonitorInfo) -> gdk::Rectangle {
+    monitors
+        .iter()
+        .fold(primary.geometry, |area, monitor| area.union(&monitor.geometry))
+}
+
+/// Resolve the anchor, offsets and size of an overlay inside an area
+fn geometry_in_area(config: &LayoutConfig, area: &gdk::Rectangle) -> gdk::Rectangle {
+    let (width, height) = match config.size_unit() {
+        SizeUnit::Pixels => (config.width(), config.height()),
+        SizeUnit::Percent => (
+            area.width() * config.width() / 100,
+            area.height() * config.height() / 100
+        )
+    };
+
+    let (horizontal, vertical) = anchor_alignment(config.anchor());
+    let x = area.x() + align(horizontal, area.width(), width, config.x());
+    let y = area.y() + align(vertical, area.height(), height, config.y());
+
+    gdk::Rectangle::new(x, y, width, height)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Alignment {
+    Start,
+    Center,
+    End
+}
+
+pub fn anchor_alignment(anchor: Anchor) -> (Alignment, Alignment) {
+    match anchor {
+        Anchor::TopLeft => (Alignment::Start, Alignment::Start),
+        Anchor::Top => (Alignment::Center, Alignment::Start),
+        Anchor::TopRight => (Alignment::End, Alignment::Start),
+        Anchor::Left => (Alignment::Start, Alignment::Center),
+        Anchor::Center => (Alignment::Center, Alignment::Center),
+        Anchor::Right => (Alignment::End, Alignment::Center),
+        Anchor::BottomLeft => (Alignment::Start, Alignment::End),
+        Anchor::Bottom => (Alignment::Center, Alignment::End),
+        Anchor::BottomRight => (Alignment::End, Alignment::End),
+    }
+}
+
+/// The position of the overlay along one axis of the area, the offset goes towards the inside
+fn align(alignment: Alignment, area_size: i32, size: i32, offset: i32) -> i32 {
+    match alignment {
+        Alignment::Start => offset,
+        Alignment::Center => (area_size - size) / 2 + offset,
+        Alignment::End => area_size - size - offset
+    }
+}
+
+/// The inverse of `align`, the offset putting the overlay at a position of the area
+fn offset_of(alignment: Alignment, area_size: i32, size: i32, position: i32) -> i32 {
+    match alignment {
+        Alignment::Start => position,
+        Alignment::Center => position - (area_size - size) / 2,
+        Alignment::End => area_size - size - position
+    }
+}
+
+/// Move a rectangle inside an area, keeping its size when it fits
+fn clamp_to(rectangle: gdk::Rectangle, area: &gdk::Rectangle) -> gdk::Rectangle {
+    let width = rectangle.width().min(area.width());
+    let height = rectangle.height().min(area.height());
+    let x = rectangle.x().clamp(area.x(), area.x() + area.width() - width);
+    let y = rectangle.y().clamp(area.y(), area.y() + area.height() - height);
+
+    gdk::Rectangle::new(x, y, width, height)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_offset_of_an_aligned_overlay_is_the_original_offset() {
+        for alignment in [Alignment::Start, Alignment::Center, Alignment::End] {
+            for area_size in [1920, 1081] {
+                for offset in [-50, 0, 37, 800] {
+                    let position = align(alignment, area_size, 301, offset);
+                    assert_eq!(offset_of(alignment, area_size, 301, position), offset, "{:?} in {}", alignment, area_size);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn the_offsets_go_towards_the_inside() {
+        assert_eq!(align(Alignment::Start, 1920, 300, 10), 10);
+        assert_eq!(align(Alignment::Center, 1920, 300, 10), 820);
+        assert_eq!(align(Alignment::End, 1920, 300, 10), 1610);
+    }
+
+    #[test]
+    fn clamping_moves_the_rectangle_inside_the_area() {
+        let area = gdk::Rectangle::new(1920, 0, 1920, 1080);
+
+        let inside = gdk::Rectangle::new(2000, 100, 300, 200);
+        assert_eq!(clamp_to(inside, &area), inside);
+
+        // Off the bottom right, then off the top left
+        assert_eq!(clamp_to(gdk::Rectangle::new(3800, 1000, 300, 200), &area), gdk::Rectangle::new(3540, 880, 300, 200));
+        assert_eq!(clamp_to(gdk::Rectangle::new(0, -50, 300, 200), &area), gdk::Rectangle::new(1920, 0, 300, 200));
+
+        // Too large, it takes the whole area
+        assert_eq!(clamp_to(gdk::Rectangle::new(0, 0, 4000, 2000), &area), area);
+    }
+
+    fn monitor(identifier: &str, geometry: gdk::Rectangle, is_primary: bool) -> MonitorInfo {
+        MonitorInfo { index: 0, identifier: identifier.to_string(), geometry, is_primary }
+    }
+
+    #[test]
+    fn monitors_are_identified_by_their_connector() {
+        assert_eq!(monitor_name(Some("DP-1"), Some("Dell"), Some("DP-1"), 0), "DP-1");
+        assert_eq!(monitor_name(None, Some(" Dell "), Some("U2720Q"), 0), "Dell U2720Q");
+        assert_eq!(monitor_name(Some(""), None, Some("U2720Q"), 0), "U2720Q");
+        assert_eq!(monitor_name(None, None, None, 1), "Monitor 2");
+    }
+
+    #[test]
+    fn identical_monitors_are_numbered() {
+        let names = ["Dell U2720Q", "Dell U2720Q", "DP-1", "Dell U2720Q"].map(str::to_string).to_vec();
+        assert_eq!(unique_identifiers(names), vec!["Dell U2720Q", "Dell U2720Q #2", "DP-1", "Dell U2720Q #3"]);
+    }
+
+    #[test]
+    fn an_overlay_on_a_disconnected_monitor_goes_to_the_primary_monitor() {
+        let monitors = [
+            monitor("DP-2", gdk::Rectangle::new(0, 0, 1920, 1080), false),
+            monitor("DP-1", gdk::Rectangle::new(1920, 0, 2560, 1440), true)
+        ];
+        let mut config = LayoutConfig::default();
+        config.set_x(100);
+        config.set_y(50);
+        config.set_width(300);
+        config.set_height(200);
+
+        config.set_monitor(Some("DP-2".to_string()));
+        assert_eq!(geometry_on_monitors(&config, &monitors), gdk::Rectangle::new(100, 50, 300, 200));
+
+        config.set_monitor(Some("HDMI-1".to_string()));
+        assert_eq!(geometry_on_monitors(&config, &monitors), gdk::Rectangle::new(2020, 50, 300, 200));
+
+        // Kept inside the primary monitor
+        config.set_x(5000);
+        assert_eq!(geometry_on_monitors(&config, &monitors), gdk::Rectangle::new(4180, 50, 300, 200));
+    }
+}
@@ -0,0 +1,155 @@
+/// Distance in pixels under which an overlay snaps to a line
+const SNAP_THRESHOLD: i32 = 8;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Bounds {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32
+}
+
+impl Bounds {
+    pub fn from_rectangle(rectangle: &gdk::Rectangle) -> Self {
+        Self {
+            x: rectangle.x(),
+            y: rectangle.y(),
+            width: rectangle.width(),
+            height: rectangle.height()
+        }
+    }
+
+    pub fn to_rectangle(self) -> gdk::Rectangle {
+        gdk::Rectangle::new(self.x, self.y, self.width, self.height)
+    }
+
+    fn contains(&self, x: i32, y: i32) -> bool {
+        x >= self.x && x < self.x + self.width && y >= self.y && y < self.y + self.height
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Guide {
+    Vertical(i32),
+    Horizontal(i32)
+}
+
+/// What a dragged overlay can snap to
+pub struct SnapTargets {
+    pub monitors: Vec<Bounds>,
+    pub overlays: Vec<Bounds>,
+    pub grid: Option<i32>
+}
+
+/// Snap the bounds of a dragged overlay, returning the new bounds and the guides to draw.
+/// Edges and centres of the other overlays and the monitor edges win over the grid.
+pub fn snap(bounds: Bounds, targets: &SnapTargets) -> (Bounds, Vec<Guide>) {
+    let mut vertical_lines = Vec::new();
+    let mut horizontal_lines = Vec::new();
+    for monitor in targets.monitors.iter() {
+        vertical_lines.extend([monitor.x, monitor.x + monitor.width]);
+        horizontal_lines.extend([monitor.y, monitor.y + monitor.height]);
+    }
+    for overlay in targets.overlays.iter() {
+        vertical_lines.extend([overlay.x, overlay.x + overlay.width / 2, overlay.x + overlay.width]);
+        horizontal_lines.extend([overlay.y, overlay.y + overlay.height / 2, overlay.y + overlay.height]);
+    }
+
+    // The grid starts at the origin of the monitor the overlay is on
+    let grid_origin = targets.monitors
+        .iter()
+        .find(|monitor| monitor.contains(bounds.x + bounds.width / 2, bounds.y + bounds.height / 2))
+        .map(|monitor| (monitor.x, monitor.y))
+        .unwrap_or_default();
+
+    let (x, vertical_guide) = snap_axis(bounds.x, bounds.width, &vertical_lines, targets.grid.map(|step| (grid_origin.0, step)));
+    let (y, horizontal_guide) = snap_axis(bounds.y, bounds.height, &horizontal_lines, targets.grid.map(|step| (grid_origin.1, step)));
+
+    let guides = vertical_guide
+        .map(Guide::Vertical)
+        .into_iter()
+        .chain(horizontal_guide.map(Guide::Horizontal))
+        .collect();
+
+    (Bounds { x, y, ..bounds }, guides)
+}
+
+/// Snap the start, centre or end of a segment to the closest line, or its start to the grid
+fn snap_axis(start: i32, size: i32, lines: &[i32], grid: Option<(i32, i32)>) -> (i32, Option<i32>) {
+    let points = [start, start + size / 2, start + size];
+
+    let closest_line = points
+        .iter()
+        .flat_map(|point| lines.iter().map(move |line| (line - point, *line)))
+        .filter(|(distance, _)| distance.abs() <= SNAP_THRESHOLD)
+        .min_by_key(|(distance, _)| distance.abs());
+
+    match (closest_line, grid) {
+        (Some((distance, line)), _) => (start + distance, Some(line)),
+        (None, Some((origin, step))) if step > 0 => {
+            let cells = ((start - origin) as f64 / step as f64).round() as i32;
+            (origin + cells * step, None)
+        },
+        _ => (start, None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bounds(x: i32, y: i32, width: i32, height: i32) -> Bounds {
+        Bounds { x, y, width, height }
+    }
+
+    fn targets(monitors: Vec<Bounds>, overlays: Vec<Bounds>, grid: Option<i32>) -> SnapTargets {
+        SnapTargets { monitors, overlays, grid }
+    }
+
+    #[test]
+    fn snaps_to_the_edges_within_the_threshold() {
+        let targets = targets(Vec::new(), vec![bounds(100, 100, 200, 100)], None);
+
+        // The left edge goes to the right edge of the other overlay
+        let (snapped, guides) = snap(bounds(300 + SNAP_THRESHOLD, 500, 50, 50), &targets);
+        assert_eq!(snapped, bounds(300, 500, 50, 50));
+        assert_eq!(guides, vec![Guide::Vertical(300)]);
+
+        // The bottom edge goes to the top edge
+        let (snapped, guides) = snap(bounds(600, 45, 50, 50), &targets);
+        assert_eq!(snapped, bounds(600, 50, 50, 50));
+        assert_eq!(guides, vec![Guide::Horizontal(100)]);
+
+        let (snapped, guides) = snap(bounds(300 + SNAP_THRESHOLD + 1, 500, 50, 50), &targets);
+        assert_eq!(snapped, bounds(309, 500, 50, 50));
+        assert!(guides.is_empty());
+    }
+
+    #[test]
+    fn snaps_the_centres_to_the_closest_line() {
+        let targets = targets(Vec::new(), vec![bounds(100, 100, 200, 100)], None);
+
+        // The centre, 5 pixels away from the centre of the other overlay, is closer than the edges
+        let (snapped, guides) = snap(bounds(170, 140, 50, 30), &targets);
+        assert_eq!(snapped, bounds(175, 135, 50, 30));
+        assert_eq!(guides, vec![Guide::Vertical(200), Guide::Horizontal(150)]);
+    }
+
+    #[test]
+    fn the_grid_starts_at_the_monitor_and_loses_to_the_lines() {
+        let targets = targets(vec![bounds(0, 0, 1920, 1080), bounds(1920, 0, 1920, 1080)], Vec::new(), Some(50));
+
+        let (snapped, guides) = snap(bounds(1993, 112, 100, 100), &targets);
+        assert_eq!(snapped, bounds(1970, 100, 100, 100));
+        assert!(guides.is_empty());
+
+        // The edge of the monitor is closer than the threshold
+        let (snapped, guides) = snap(bounds(1925, 112, 100, 100), &targets);
+        assert_eq!(snapped, bounds(1920, 100, 100, 100));
+        assert_eq!(guides, vec![Guide::Vertical(1920)]);
+
+        // No grid step, nothing to snap to
+        let (snapped, _) = snap(bounds(1993, 112, 100, 100), &SnapTargets { grid: Some(0), ..targets });
+        assert_eq!(snapped, bounds(1993, 112, 100, 100));
+    }
+}
@@ -0,0 +1,105 @@
+use crate::layout_config::{CombatCondition, LayoutConfig, Visibility, VisibilityConditions};
+use crate::relay::game_state::GameState;
+
+use super::game_window::FocusedWindow;
+
+/// What the visibility of the overlays depends on
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct VisibilityState {
+    /// Unknown when the focus can not be tracked
+    pub focus: Option<FocusedWindow>,
+    pub game_state: GameState
+}
+
+impl VisibilityState {
+    /// Whether the rules of an overlay let it be shown
+    pub fn allows(&self, config: &LayoutConfig) -> bool {
+        matches_focus(config.visibility(), self.focus) && matches_conditions(config.conditions(), &self.game_state)
+    }
+}
+
+/// An unknown focus never hides an overlay
+fn matches_focus(visibility: Visibility, focus: Option<FocusedWindow>) -> bool {
+    match (visibility, focus) {
+        (Visibility::Always, _) | (_, None) => true,
+        (Visibility::GameFocused, Some(focus)) => focus == FocusedWindow::Game,
+        (Visibility::GameOrManagerFocused, Some(focus)) => focus != FocusedWindow::Other
+    }
+}
+
+/// A zone or job list does not match while the zone or job is not known yet
+fn matches_conditions(conditions: &VisibilityConditions, game_state: &GameState) -> bool {
+    let matches_combat = match conditions.combat {
+        CombatCondition::Any => true,
+        CombatCondition::InCombat => game_state.is_in_combat,
+        CombatCondition::OutOfCombat => !game_state.is_in_combat
+    };
+    let matches_zone = conditions.zones.is_empty()
+        || game_state.zone_id.map(|zone_id| conditions.zones.contains(&zone_id)).unwrap_or(false);
+    let matches_job = conditions.jobs.is_empty()
+        || game_state.job
+            .as_ref()
+            .map(|job| conditions.jobs.iter().any(|allowed_job| allowed_job.eq_ignore_ascii_case(job)))
+            .unwrap_or(false);
+
+    matches_combat && matches_zone && matches_job
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn game_state(zone_id: Option<u32>, is_in_combat: bool, job: Option<&str>) -> GameState {
+        let mut game_state = GameState::default();
+        game_state.zone_id = zone_id;
+        game_state.is_in_combat = is_in_combat;
+        game_state.job = job.map(str::to_string);
+        game_state
+    }
+
+    fn conditions(combat: CombatCondition, zones: &[u32], jobs: &[&str]) -> VisibilityConditions {
+        VisibilityConditions {
+            combat,
+            zones: zones.to_vec(),
+            jobs: jobs.iter().map(|job| job.to_string()).collect()
+        }
+    }
+
+    #[test]
+    fn no_condition_matches_any_state() {
+        let conditions = VisibilityConditions::default();
+        assert!(matches_conditions(&conditions, &GameState::default()));
+        assert!(matches_conditions(&conditions, &game_state(Some(1), true, Some("WHM"))));
+    }
+
+    #[test]
+    fn matches_the_combat() {
+        let (in_combat, out_of_combat) = (game_state(None, true, None), game_state(None, false, None));
+        assert!(matches_conditions(&conditions(CombatCondition::InCombat, &[], &[]), &in_combat));
+        assert!(!matches_conditions(&conditions(CombatCondition::InCombat, &[], &[]), &out_of_combat));
+        assert!(matches_conditions(&conditions(CombatCondition::OutOfCombat, &[], &[]), &out_of_combat));
+        assert!(!matches_conditions(&conditions(CombatCondition::OutOfCombat, &[], &[]), &in_combat));
+    }
+
+    #[test]
+    fn an_unknown_zone_or_job_does_not_match_a_list() {
+        let zones = conditions(CombatCondition::Any, &[1122, 1123], &[]);
+        assert!(matches_conditions(&zones, &game_state(Some(1123), false, None)));
+        assert!(!matches_conditions(&zones, &game_state(Some(128), false, None)));
+        assert!(!matches_conditions(&zones, &GameState::default()));
+
+        let jobs = conditions(CombatCondition::Any, &[], &["whm", "SCH"]);
+        assert!(matches_conditions(&jobs, &game_state(None, false, Some("WHM"))));
+        assert!(!matches_conditions(&jobs, &game_state(None, false, Some("PLD"))));
+        assert!(!matches_conditions(&jobs, &GameState::default()));
+    }
+
+    #[test]
+    fn every_condition_must_match() {
+        let conditions = conditions(CombatCondition::InCombat, &[1122], &["WHM"]);
+        assert!(matches_conditions(&conditions, &game_state(Some(1122), true, Some("WHM"))));
+        assert!(!matches_conditions(&conditions, &game_state(Some(1122), false, Some("WHM"))));
+        assert!(!matches_conditions(&conditions, &game_state(Some(1), true, Some("WHM"))));
+        assert!(!matches_conditions(&conditions, &game_state(Some(1122), true, Some("AST"))));
+    }
+}
@@ -0,0 +1,181 @@
+use serde_json::{Map, Value};
+
+
+/// The events the state of the game is read from
+pub const GAME_STATE_EVENTS: [&str; 4] = [
+    "ChangeZone",
+    "InCombat",
+    "ChangePrimaryPlayer",
+    "CombatData",
+];
+
+/// Job abbreviations by the ClassJob id getCombatants reports
+const JOBS: [&str; 43] = [
+    "ADV", "GLA", "PGL", "MRD", "LNC", "ARC", "CNJ", "THM", "CRP", "BSM", "ARM",
+    "GSM", "LTW", "WVR", "ALC", "CUL", "MIN", "BTN", "FSH", "PLD", "MNK", "WAR",
+    "DRG", "BRD", "WHM", "BLM", "ACN", "SMN", "SCH", "ROG", "NIN", "MCH", "DRK",
+    "AST", "SAM", "RDM", "BLU", "GNB", "DNC", "RPR", "SGE", "VPR", "PCT",
+];
+
+/// What the overlays visibility conditions are evaluated against
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct GameState {
+    pub zone_id: Option<u32>,
+    pub is_in_combat: bool,
+    /// Job abbreviation of the player, in uppercase
+    pub job: Option<String>,
+    player_id: Option<u64>,
+    player_name: Option<String>
+}
+
+impl GameState {
+    /// Update the state from an event, returns whether it changed
+    pub fn update(&mut self, event_type: &str, text: &str) -> bool {
+        if !GAME_STATE_EVENTS.contains(&event_type) {
+            return false;
+        }
+        let Ok(message) = serde_json::from_str::<Value>(text) else {
+            return false;
+        };
+
+        let previous_state = self.clone();
+        match event_type {
+            "ChangeZone" => {
+                self.zone_id = message.get("zoneID").and_then(Value::as_u64).map(|zone_id| zone_id as u32);
+            },
+            "InCombat" => {
+                let is_flag_set = |name: &str| message.get(name).and_then(Value::as_bool).unwrap_or(false);
+                self.is_in_combat = is_flag_set("inGameCombat") || is_flag_set("inACTCombat");
+            },
+            "ChangePrimaryPlayer" => {
+                self.player_id = message.get("charID").and_then(Value::as_u64);
+                self.player_name = message.get("charName").and_then(Value::as_str).map(str::to_string);
+            },
+            "CombatData" => {
+                // ACT names the player YOU by default
+                let combatants = message.get("Combatant");
+                let player = self.player_name
+                    .as_deref()
+                    .and_then(|name| combatants.and_then(|combatants| combatants.get(name)))
+                    .or_else(|| combatants.and_then(|combatants| combatants.get("YOU")));
+                if let Some(job) = player.and_then(|player| player.get("Job")).and_then(Value::as_str) {
+                    self.job = Some(job.to_uppercase()).filter(|job| !job.is_empty());
+                }
+            },
+            _ => {}
+        }
+
+        *self != previous_state
+    }
+
+    /// The getCombatants call returning the player, CombatData only has its job during the fights
+    pub fn player_call(&self) -> Option<Map<String, Value>> {
+        let mut call = Map::new();
+        call.insert("call".into(), "getCombatants".into());
+        match (self.player_id, &self.player_name) {
+            (Some(player_id), _) => call.insert("ids".into(), vec![player_id].into()),
+            (None, Some(player_name)) => call.insert("names".into(), vec![player_name.clone()].into()),
+            (None, None) => return None
+        };
+
+        Some(call)
+    }
+
+    /// Update the job from the response to the player call, returns whether it changed
+    pub fn update_from_combatants(&mut self, response: &Value) -> bool {
+        let job = response
+            .get("combatants")
+            .and_then(Value::as_array)
+            .and_then(|combatants| combatants.iter().find(|combatant| self.is_player(combatant)))
+            .and_then(|player| player.get("Job"))
+            .and_then(job_abbreviation);
+
+        match job {
+            Some(job) if self.job.as_ref() != Some(&job) => {
+                self.job = Some(job);
+                true
+            },
+            _ => false
+        }
+    }
+
+    fn is_player(&self, combatant: &Value) -> bool {
+        match (self.player_id, &self.player_name) {
+            (Some(player_id), _) => combatant.get("ID").and_then(Value::as_u64) == Some(player_id),
+            (None, Some(player_name)) => combatant.get("Name").and_then(Value::as_str) == Some(player_name.as_str()),
+            (None, None) => false
+        }
+    }
+}
+
+/// getCombatants gives the id of the job, older versions its abbreviation
+fn job_abbreviation(job: &Value) -> Option<String> {
+    match job {
+        Value::Number(_) => job.as_u64().and_then(|job_id| JOBS.get(job_id as usize)).map(|job| job.to_string()),
+        Value::String(job) => Some(job.to_uppercase()).filter(|job| !job.is_empty()),
+        _ => None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ignores_the_other_and_invalid_events() {
+        let mut game_state = GameState::default();
+        assert!(!game_state.update("LogLine", r#"{"type":"LogLine","line":[]}"#));
+        assert!(!game_state.update("ChangeZone", "{not json"));
+        assert_eq!(game_state, GameState::default());
+    }
+
+    #[test]
+    fn follows_the_zone_and_the_combat() {
+        let mut game_state = GameState::default();
+        assert!(game_state.update("ChangeZone", r#"{"type":"ChangeZone","zoneID":1122}"#));
+        assert_eq!(game_state.zone_id, Some(1122));
+        assert!(!game_state.update("ChangeZone", r#"{"type":"ChangeZone","zoneID":1122}"#));
+
+        assert!(game_state.update("InCombat", r#"{"type":"InCombat","inACTCombat":false,"inGameCombat":true}"#));
+        assert!(game_state.is_in_combat);
+        assert!(game_state.update("InCombat", r#"{"type":"InCombat","inACTCombat":false,"inGameCombat":false}"#));
+        assert!(!game_state.is_in_combat);
+    }
+
+    #[test]
+    fn reads_the_job_of_the_player_in_the_combat_data() {
+        let mut game_state = GameState::default();
+        let combat_data = r#"{"type":"CombatData","Combatant":{"YOU":{"Job":"whm"},"Other":{"Job":"pld"}}}"#;
+        assert!(game_state.update("CombatData", combat_data));
+        assert_eq!(game_state.job.as_deref(), Some("WHM"));
+
+        // Once its name is known, the player is looked up by name
+        game_state.update("ChangePrimaryPlayer", r#"{"type":"ChangePrimaryPlayer","charID":275,"charName":"Other"}"#);
+        assert!(game_state.update("CombatData", combat_data));
+        assert_eq!(game_state.job.as_deref(), Some("PLD"));
+
+        // A player missing from the combat data keeps the known job
+        assert!(!game_state.update("CombatData", r#"{"type":"CombatData","Combatant":{"Someone":{"Job":"sch"}}}"#));
+        assert_eq!(game_state.job.as_deref(), Some("PLD"));
+    }
+
+    #[test]
+    fn reads_the_job_of_the_player_in_the_combatants() {
+        let mut game_state = GameState::default();
+        assert_eq!(game_state.player_call(), None);
+
+        game_state.update("ChangePrimaryPlayer", r#"{"type":"ChangePrimaryPlayer","charID":275,"charName":"Player"}"#);
+        let call = game_state.player_call().unwrap();
+        assert_eq!(Value::Object(call).to_string(), r#"{"call":"getCombatants","ids":[275]}"#);
+
+        let response = serde_json::from_str::<Value>(r#"{"combatants":[{"ID":12,"Job":19},{"ID":275,"Name":"Player","Job":24}]}"#).unwrap();
+        assert!(game_state.update_from_combatants(&response));
+        assert_eq!(game_state.job.as_deref(), Some("WHM"));
+        assert!(!game_state.update_from_combatants(&response));
+
+        // Unknown jobs and missing players keep the known job
+        assert!(!game_state.update_from_combatants(&serde_json::from_str::<Value>(r#"{"combatants":[{"ID":275,"Job":500}]}"#).unwrap()));
+        assert!(!game_state.update_from_combatants(&serde_json::from_str::<Value>(r#"{"combatants":[]}"#).unwrap()));
+        assert_eq!(game_state.job.as_deref(), Some("WHM"));
+    }
+}
@@ -0,0 +1,995 @@
+//! Local WebSocket relay shared by every overlay.
+//!
+//! The relay keeps a single connection to the OverlayPlugin server (IINACT) and
+//! serves a local endpoint the overlays connect to instead. Events are fanned out
+//! to the overlays that subscribed to them, calls are forwarded upstream and their
+//! responses routed back to the overlay that made them. The state of the game
+//! (zone, combat, job) the visibility conditions of the overlays depend on is read
+//! from the events on the way.
+pub mod game_state;
+pub mod recorder;
+pub mod replay;
+pub mod synthetic;
+
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::rc::{Rc, Weak};
+use std::time::Duration;
+
+use async_channel::Sender;
+use glib::translate::IntoGlib;
+use serde_json::{Map, Value};
+use soup::prelude::*;
+
+use crate::app::AppAction;
+use crate::app_config::AppConfig;
+
+use self::game_state::{GameState, GAME_STATE_EVENTS};
+use self::recorder::{Recorder, RECORDED_EVENTS};
+use self::replay::{Replay, ReplayStatus};
+use self::synthetic::{SyntheticConfig, SyntheticEncounter};
+
+const RELAY_PATH: &str = "/ws";
+/// The query parameter the OverlayPlugin overlays read the websocket url from
+pub const OVERLAY_WS_PARAM: &str = "OVERLAY_WS";
+/// The websocket parameter of the overlays made for ACTWebSocket
+const HOST_PORT_PARAM: &str = "HOST_PORT";
+const MIN_RECONNECT_DELAY: Duration = Duration::from_secs(1);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+const SOURCE_TICK: Duration = Duration::from_millis(100);
+
+struct RelayClient {
+    connection: soup::WebsocketConnection,
+    subscriptions: HashSet<String>
+}
+
+/// Where the events sent to the overlays come from, instead of the upstream server
+enum EventSource {
+    Replay(Replay),
+    Synthetic(SyntheticEncounter)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EventSourceStatus {
+    Live,
+    Replay(ReplayStatus),
+    Synthetic
+}
+
+struct PendingCall {
+    client_id: u32,
+    rseq: Value
+}
+
+struct RelayState {
+    upstream: Option<soup::WebsocketConnection>,
+    clients: HashMap<u32, RelayClient>,
+    next_client_id: u32,
+    pending_calls: HashMap<u64, PendingCall>,
+    /// The rseq of the call the relay made for the job of the player
+    player_call: Option<u64>,
+    next_rseq: u64,
+    reconnect_delay: Duration,
+    // Events the relay needs for itself, whether an overlay wants them or not
+    relay_subscriptions: HashSet<String>,
+    recorder: Option<Recorder>,
+    /// The relay subscriptions added for the recording, removed once it stops
+    recording_subscriptions: HashSet<String>,
+    /// The last status sent to the manager
+    source_status: EventSourceStatus,
+    source: Option<EventSource>,
+    source_timer: Option<glib::SourceId>,
+    game_state: GameState
+}
+
+impl RelayState {
+    fn subscriptions(&self) -> HashSet<String> {
+        self.clients
+            .values()
+            .flat_map(|client| client.subscriptions.iter().cloned())
+            .chain(self.relay_subscriptions.iter().cloned())
+            .collect()
+    }
+}
+
+struct RelayInner {
+    upstream_url: String,
+    local_url: String,
+    recordings_path: PathBuf,
+    event_sender: Sender<AppAction>,
+    session: soup::Session,
+    // Kept alive for as long as the relay exists
+    _server: soup::Server,
+    state: RefCell<RelayState>
+}
+
+#[derive(Clone)]
+pub struct Relay {
+    inner: Rc<RelayInner>
+}
+
+impl Relay {
+    pub fn start(app_config: &AppConfig, event_sender: Sender<AppAction>) -> Result<Relay, glib::Error> {
+        let server: soup::Server = glib::Object::new(&[]);
+        server.listen_local(app_config.relay_port(), soup::ServerListenOptions::IPV4_ONLY)?;
+        let port = listening_port(&server).unwrap_or(app_config.relay_port());
+
+        let relay = Relay {
+            inner: Rc::new(RelayInner {
+                upstream_url: app_config.relay_upstream_url().to_string(),
+                local_url: format!("ws://127.0.0.1:{}{}", port, RELAY_PATH),
+                recordings_path: app_config.recordings_path().to_path_buf(),
+                event_sender,
+                session: soup::Session::new(),
+                _server: server.clone(),
+                state: RefCell::new(RelayState {
+                    upstream: None,
+                    clients: HashMap::new(),
+                    next_client_id: 0,
+                    pending_calls: HashMap::new(),
+                    player_call: None,
+                    next_rseq: 0,
+                    reconnect_delay: MIN_RECONNECT_DELAY,
+                    relay_subscriptions: GAME_STATE_EVENTS.iter().map(|event| event.to_string()).collect(),
+                    recorder: None,
+                    recording_subscriptions: HashSet::new(),
+                    source_status: EventSourceStatus::Live,
+                    source: None,
+                    source_timer: None,
+                    game_state: GameState::default()
+                })
+            })
+        };
+
+        let weak_relay = relay.downgrade();
+        server.add_websocket_handler(Some(RELAY_PATH), None, &[], move |_, _, _, connection| {
+            if let Some(relay) = Relay::upgrade(&weak_relay) {
+                relay.add_client(connection);
+            }
+        });
+
+        info!("Relay listening on {}", relay.local_url());
+        relay.connect_upstream();
+
+        Ok(relay)
+    }
+
+    pub fn local_url(&self) -> &str {
+        &self.inner.local_url
+    }
+
+    /// Point the OverlayPlugin websocket of an overlay url to the relay
+    pub fn overlay_url(&self, url: &str) -> String {
+        relayed_url(url, self.local_url())
+    }
+
+    fn downgrade(&self) -> Weak<RelayInner> {
+        Rc::downgrade(&self.inner)
+    }
+
+    fn upgrade(weak_relay: &Weak<RelayInner>) -> Option<Relay> {
+        weak_relay.upgrade().map(|inner| Relay { inner })
+    }
+
+    fn connect_upstream(&self) {
+        let message = match soup::Message::new("GET", &self.inner.upstream_url) {
+            Ok(message) => message,
+            Err(error) => {
+                error!("Invalid relay upstream url {}: {}", self.inner.upstream_url, error);
+                return;
+            }
+        };
+
+        let weak_relay = self.downgrade();
+        self.inner.session.websocket_connect_async(
+            &message,
+            None,
+            &[],
+            glib::Priority::default(),
+            None::<&gio::Cancellable>,
+            move |result| {
+                let Some(relay) = Relay::upgrade(&weak_relay) else {
+                    return;
+                };
+
+                match result {
+                    Ok(connection) => relay.on_upstream_connected(connection),
+                    Err(error) => {
+                        warn!("Could not connect to {}: {}", relay.inner.upstream_url, error);
+                        relay.schedule_reconnect();
+                    }
+                }
+            }
+        );
+    }
+
+    fn schedule_reconnect(&self) {
+        let delay = {
+            let mut state = self.inner.state.borrow_mut();
+            let delay = state.reconnect_delay;
+            state.reconnect_delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+            delay
+        };
+
+        info!("Reconnecting to the upstream server in {:?}", delay);
+        let weak_relay = self.downgrade();
+        glib::timeout_add_local_once(delay, move || {
+            if let Some(relay) = Relay::upgrade(&weak_relay) {
+                relay.connect_upstream();
+            }
+        });
+    }
+
+    fn on_upstream_connected(&self, connection: soup::WebsocketConnection) {
+        info!("Relay connected to {}", self.inner.upstream_url);
+
+        let weak_relay = self.downgrade();
+        connection.connect_message(move |_, data_type, data| {
+            if let (Some(relay), Some(text)) = (Relay::upgrade(&weak_relay), text_message(data_type, data)) {
+                relay.on_upstream_message(text);
+            }
+        });
+
+        let weak_relay = self.downgrade();
+        connection.connect_closed(move |_| {
+            if let Some(relay) = Relay::upgrade(&weak_relay) {
+                relay.on_upstream_closed();
+            }
+        });
+
+        self.notify_upstream_status(true);
+
+        let subscriptions = {
+            let mut state = self.inner.state.borrow_mut();
+            state.reconnect_delay = MIN_RECONNECT_DELAY;
+            state.upstream = Some(connection.clone());
+            state.subscriptions()
+        };
+
+        // Replay the subscriptions of the overlays that are already connected
+        if !subscriptions.is_empty() {
+            connection.send_text(&subscribe_message(subscriptions));
+        }
+    }
+
+    fn on_upstream_closed(&self) {
+        warn!("Relay lost the connection to {}", self.inner.upstream_url);
+
+        {
+            let mut state = self.inner.state.borrow_mut();
+            state.upstream = None;
+            // The responses of these calls will never come
+            state.pending_calls.clear();
+            state.player_call = None;
+        }
+
+        self.notify_upstream_status(false);
+        self.schedule_reconnect();
+    }
+
+    fn on_upstream_message(&self, text: &str) {
+        let Ok(mut message) = serde_json::from_str::<Value>(text) else {
+            warn!("Relay received an invalid message from the upstream server");
+            return;
+        };
+
+        let rseq = message.get("rseq").and_then(Value::as_u64);
+        if rseq.is_some() && rseq == self.inner.state.borrow().player_call {
+            let game_state = {
+                let mut state = self.inner.state.borrow_mut();
+                state.player_call = None;
+                state.game_state.update_from_combatants(&message).then(|| state.game_state.clone())
+            };
+            if let Some(game_state) = game_state {
+                self.notify_game_state(game_state);
+            }
+            return;
+        }
+
+        // Responses to calls are routed back to the overlay that made them
+        let pending_call = message
+            .get("rseq")
+            .and_then(Value::as_u64)
+            .and_then(|rseq| self.inner.state.borrow_mut().pending_calls.remove(&rseq));
+        if let Some(pending_call) = pending_call {
+            if let Value::Object(mapping) = &mut message {
+                mapping.insert("rseq".into(), pending_call.rseq);
+            }
+            let connection = self.inner.state
+                .borrow()
+                .clients
+                .get(&pending_call.client_id)
+                .map(|client| client.connection.clone());
+            if let Some(connection) = connection {
+                connection.send_text(&message.to_string());
+            }
+            return;
+        }
+
+        let Some(event_type) = message.get("type").and_then(Value::as_str) else {
+            return;
+        };
+
+        let mut state = self.inner.state.borrow_mut();
+        if let Some(recorder) = state.recorder.as_mut() {
+            if let Err(error) = recorder.record(text) {
+                error!("Could not write to the recording {:?}, stopping it. Error {:?}", recorder.path(), error);
+                state.recorder = None;
+            }
+        }
+
+        // The live events are muted while another source is used
+        let is_live = state.source.is_none();
+        drop(state);
+        if is_live {
+            self.dispatch_event(event_type, text);
+            if matches!(event_type, "ChangePrimaryPlayer" | "ChangeZone") {
+                self.request_player_job();
+            }
+        }
+    }
+
+    /// Changing the job also changes the zone, which is when the player is looked up
+    fn request_player_job(&self) {
+        let mut state = self.inner.state.borrow_mut();
+        let (Some(upstream), Some(mut call)) = (state.upstream.clone(), state.game_state.player_call()) else {
+            return;
+        };
+
+        let rseq = state.next_rseq;
+        state.next_rseq += 1;
+        state.player_call = Some(rseq);
+        drop(state);
+
+        call.insert("rseq".into(), rseq.into());
+        upstream.send_text(&Value::Object(call).to_string());
+    }
+
+    /// Send an event to every overlay subscribed to its type
+    fn dispatch_event(&self, event_type: &str, text: &str) {
+        // Whatever the source, the visibility of the overlays follows the events they receive
+        let game_state = {
+            let mut state = self.inner.state.borrow_mut();
+            state.game_state.update(event_type, text).then(|| state.game_state.clone())
+        };
+        if let Some(game_state) = game_state {
+            self.notify_game_state(game_state);
+        }
+
+        let connections: Vec<soup::WebsocketConnection> = self.inner.state
+            .borrow()
+            .clients
+            .values()
+            .filter(|client| client.subscriptions.contains(event_type))
+            .map(|client| client.connection.clone())
+            .collect();
+
+        for connection in connections {
+            connection.send_text(text);
+        }
+    }
+
+    fn add_client(&self, connection: &soup::WebsocketConnection) {
+        let client_id = {
+            let mut state = self.inner.state.borrow_mut();
+            let client_id = state.next_client_id;
+            state.next_client_id += 1;
+            state.clients.insert(client_id, RelayClient {
+                connection: connection.clone(),
+                subscriptions: HashSet::new()
+            });
+            client_id
+        };
+        debug!("Overlay connected to the relay ({})", client_id);
+
+        let weak_relay = self.downgrade();
+        connection.connect_message(move |_, data_type, data| {
+            if let (Some(relay), Some(text)) = (Relay::upgrade(&weak_relay), text_message(data_type, data)) {
+                relay.on_client_message(client_id, text);
+            }
+        });
+
+        let weak_relay = self.downgrade();
+        connection.connect_closed(move |_| {
+            if let Some(relay) = Relay::upgrade(&weak_relay) {
+                debug!("Overlay disconnected from the relay ({})", client_id);
+                let mut state = relay.inner.state.borrow_mut();
+                state.clients.remove(&client_id);
+                state.pending_calls.retain(|_, call| call.client_id != client_id);
+            }
+        });
+    }
+
+    fn on_client_message(&self, client_id: u32, text: &str) {
+        let Ok(Value::Object(mut message)) = serde_json::from_str::<Value>(text) else {
+            warn!("Relay received an invalid message from an overlay ({})", client_id);
+            return;
+        };
+
+        let events: Vec<String> = message
+            .get("events")
+            .and_then(Value::as_array)
+            .map(|events| events.iter().filter_map(Value::as_str).map(String::from).collect())
+            .unwrap_or_default();
+
+        match message.get("call").and_then(Value::as_str) {
+            Some("subscribe") => self.subscribe(client_id, events),
+            Some("unsubscribe") => {
+                if let Some(client) = self.inner.state.borrow_mut().clients.get_mut(&client_id) {
+                    for event in events.iter() {
+                        client.subscriptions.remove(event);
+                    }
+                }
+            },
+            _ => {
+                let Some(upstream) = self.inner.state.borrow().upstream.clone() else {
+                    warn!("Dropping a call from an overlay ({}), the upstream server is not connected", client_id);
+                    return;
+                };
+
+                // Give the call a relay wide sequence number so that overlays can not collide
+                if let Some(rseq) = message.remove("rseq") {
+                    let mut state = self.inner.state.borrow_mut();
+                    let relay_rseq = state.next_rseq;
+                    state.next_rseq += 1;
+                    state.pending_calls.insert(relay_rseq, PendingCall { client_id, rseq });
+                    message.insert("rseq".into(), relay_rseq.into());
+                }
+
+                upstream.send_text(&Value::Object(message).to_string());
+            }
+        }
+    }
+
+    pub fn start_recording(&self) -> Result<PathBuf, std::io::Error> {
+        let recorder = Recorder::create(&self.inner.recordings_path)?;
+        let path = recorder.path().to_path_buf();
+        info!("Recording the upstream events to {:?}", path);
+
+        let (new_events, upstream) = {
+            let mut state = self.inner.state.borrow_mut();
+            let subscriptions = state.subscriptions();
+            let new_events: HashSet<String> = RECORDED_EVENTS
+                .iter()
+                .map(|event| event.to_string())
+                .filter(|event| !subscriptions.contains(event))
+                .collect();
+
+            state.relay_subscriptions.extend(new_events.iter().cloned());
+            state.recording_subscriptions.extend(new_events.iter().cloned());
+            state.recorder = Some(recorder);
+            (new_events, state.upstream.clone())
+        };
+
+        if let (false, Some(upstream)) = (new_events.is_empty(), upstream) {
+            upstream.send_text(&subscribe_message(new_events));
+        }
+
+        Ok(path)
+    }
+
+    pub fn stop_recording(&self) -> Option<Result<PathBuf, std::io::Error>> {
+        let (recorder, unused_events, upstream) = {
+            let mut state = self.inner.state.borrow_mut();
+            let recording_subscriptions = std::mem::take(&mut state.recording_subscriptions);
+            for event in recording_subscriptions.iter() {
+                state.relay_subscriptions.remove(event);
+            }
+            // The overlays may have subscribed to them since
+            let subscriptions = state.subscriptions();
+            let unused_events: HashSet<String> = recording_subscriptions
+                .into_iter()
+                .filter(|event| !subscriptions.contains(event))
+                .collect();
+            (state.recorder.take(), unused_events, state.upstream.clone())
+        };
+
+        if let (false, Some(upstream)) = (unused_events.is_empty(), upstream) {
+            upstream.send_text(&subscription_message("unsubscribe", unused_events));
+        }
+
+        recorder.map(Recorder::finish)
+    }
+
+    pub fn start_replay(&self, path: &Path) -> Result<(), std::io::Error> {
+        let replay = Replay::load(path)?;
+        info!("Replaying the recording {:?} ({:?})", path, replay.duration());
+
+        self.set_source(EventSource::Replay(replay));
+        Ok(())
+    }
+
+    pub fn start_synthetic(&self, config: SyntheticConfig) {
+        info!("Generating synthetic events for {:?}", config);
+
+        let encounter = SyntheticEncounter::new(config);
+        let events = encounter.start_events();
+        self.set_source(EventSource::Synthetic(encounter));
+
+        for (event_type, text) in events {
+            self.dispatch_event(&event_type, &text);
+        }
+    }
+
+    pub fn use_live_events(&self) {
+        let mut state = self.inner.state.borrow_mut();
+        if let Some(source_timer) = state.source_timer.take() {
+            source_timer.remove();
+        }
+        if state.source.take().is_some() {
+            drop(state);
+            info!("Back to the live events");
+            self.notify_source_status();
+        }
+    }
+
+    pub fn set_replay_paused(&self, is_paused: bool) {
+        if let Some(EventSource::Replay(replay)) = self.inner.state.borrow_mut().source.as_mut() {
+            replay.set_paused(is_paused);
+        }
+        self.notify_source_status();
+    }
+
+    pub fn set_replay_speed(&self, speed: f64) {
+        if let Some(EventSource::Replay(replay)) = self.inner.state.borrow_mut().source.as_mut() {
+            replay.set_speed(speed);
+        }
+        self.notify_source_status();
+    }
+
+    pub fn seek_replay(&self, position: Duration) {
+        let events: Vec<(String, String)> = match self.inner.state.borrow_mut().source.as_mut() {
+            Some(EventSource::Replay(replay)) => replay
+                .seek(position)
+                .into_iter()
+                .map(|event| (event.event_type.clone(), event.text.clone()))
+                .collect(),
+            _ => return
+        };
+
+        for (event_type, text) in events {
+            self.dispatch_event(&event_type, &text);
+        }
+        self.notify_source_status();
+    }
+
+    fn set_source(&self, source: EventSource) {
+        let mut state = self.inner.state.borrow_mut();
+        state.source = Some(source);
+        // The timer of a previous source keeps running for the new one
+        if state.source_timer.is_none() {
+            let weak_relay = self.downgrade();
+            state.source_timer = Some(glib::timeout_add_local(SOURCE_TICK, move || {
+                match Relay::upgrade(&weak_relay) {
+                    Some(relay) => relay.tick_source(),
+                    None => glib::Continue(false)
+                }
+            }));
+        }
+        drop(state);
+
+        self.notify_source_status();
+    }
+
+    fn tick_source(&self) -> glib::Continue {
+        let mut state = self.inner.state.borrow_mut();
+        let (events, is_replay): (Vec<(String, String)>, bool) = match state.source.as_mut() {
+            Some(EventSource::Replay(replay)) => (
+                replay
+                    .due_events()
+                    .iter()
+                    .map(|event| (event.event_type.clone(), event.text.clone()))
+                    .collect(),
+                true
+            ),
+            Some(EventSource::Synthetic(encounter)) => (encounter.tick(), false),
+            None => {
+                state.source_timer = None;
+                return glib::Continue(false);
+            }
+        };
+        drop(state);
+
+        for (event_type, text) in events {
+            self.dispatch_event(&event_type, &text);
+        }
+        // Keep the position of the replay up to date in the manager
+        if is_replay {
+            self.notify_source_status();
+        }
+
+        glib::Continue(true)
+    }
+
+    fn notify_upstream_status(&self, is_connected: bool) {
+        let event_sender = self.inner.event_sender.clone();
+        glib::MainContext::default().spawn_local(async move {
+            let _ = event_sender.send(AppAction::UpstreamStatusChanged(is_connected)).await;
+        });
+    }
+
+    fn notify_game_state(&self, game_state: GameState) {
+        let event_sender = self.inner.event_sender.clone();
+        glib::MainContext::default().spawn_local(async move {
+            let _ = event_sender.send(AppAction::GameStateChanged(game_state)).await;
+        });
+    }
+
+    /// Only sent when it changed, a paused replay is ticked without moving
+    fn notify_source_status(&self) {
+        let mut state = self.inner.state.borrow_mut();
+        let status = match state.source.as_ref() {
+            Some(EventSource::Replay(replay)) => EventSourceStatus::Replay(replay.status()),
+            Some(EventSource::Synthetic(_)) => EventSourceStatus::Synthetic,
+            None => EventSourceStatus::Live
+        };
+        if std::mem::replace(&mut state.source_status, status) == status {
+            return;
+        }
+        drop(state);
+
+        let event_sender = self.inner.event_sender.clone();
+        glib::MainContext::default().spawn_local(async move {
+            let _ = event_sender.send(AppAction::EventSourceChanged(status)).await;
+        });
+    }
+
+    fn subscribe(&self, client_id: u32, events: Vec<String>) {
+        let (new_events, upstream, start_events) = {
+            let mut state = self.inner.state.borrow_mut();
+            let subscriptions = state.subscriptions();
+            let new_events: HashSet<String> = events
+                .iter()
+                .filter(|event| !subscriptions.contains(*event))
+                .cloned()
+                .collect();
+
+            // The zone and party of a synthetic encounter are only generated when it starts
+            let start_events: Vec<String> = match state.source.as_ref() {
+                Some(EventSource::Synthetic(encounter)) => encounter
+                    .start_events()
+                    .into_iter()
+                    .filter(|(event_type, _)| events.contains(event_type))
+                    .map(|(_, text)| text)
+                    .collect(),
+                _ => Vec::new()
+            };
+
+            let connection = state.clients.get_mut(&client_id).map(|client| {
+                client.subscriptions.extend(events);
+                client.connection.clone()
+            });
+
+            (new_events, state.upstream.clone(), connection.map(|connection| (connection, start_events)))
+        };
+
+        if let Some((connection, start_events)) = start_events {
+            for text in start_events {
+                connection.send_text(&text);
+            }
+        }
+
+        // Subscriptions are replayed on connection when the upstream server is not there yet
+        if let (false, Some(upstream)) = (new_events.is_empty(), upstream) {
+            upstream.send_text(&subscribe_message(new_events));
+        }
+    }
+}
+
+/// The port the server listens on, which the system picks when asked for port 0
+fn listening_port(server: &soup::Server) -> Option<u32> {
+    server
+        .uris()
+        .iter()
+        .map(|uri| uri.port())
+        .find(|port| *port > 0)
+        .map(|port| port as u32)
+}
+
+/// Set the websocket url of an overlay url to the relay, adding it when the url has none.
+/// The legacy `HOST_PORT` is replaced by `OVERLAY_WS`, which OverlayPlugin's common.js prefers.
+fn relayed_url(url: &str, local_url: &str) -> String {
+    let (base, fragment) = match url.split_once('#') {
+        Some((base, fragment)) => (base, Some(fragment)),
+        None => (url, None)
+    };
+    let (path, query) = base.split_once('?').unwrap_or((base, ""));
+
+    let websocket_param = format!("{}={}", OVERLAY_WS_PARAM, local_url);
+    let mut params: Vec<String> = Vec::new();
+    for param in query.split('&').filter(|param| !param.is_empty()) {
+        let name = param.split_once('=').map(|(name, _)| name).unwrap_or(param);
+        if name != OVERLAY_WS_PARAM && name != HOST_PORT_PARAM {
+            params.push(param.to_string());
+        } else if !params.contains(&websocket_param) {
+            params.push(websocket_param.clone());
+        }
+    }
+    if !params.contains(&websocket_param) {
+        params.push(websocket_param);
+    }
+    let query = params.join("&");
+
+    match fragment {
+        Some(fragment) => format!("{}?{}#{}", path, query, fragment),
+        None => format!("{}?{}", path, query)
+    }
+}
+
+fn text_message(data_type: i32, data: &glib::Bytes) -> Option<&str> {
+    if data_type != soup::WebsocketDataType::Text.into_glib() {
+        return None;
+    }
+    std::str::from_utf8(data).ok()
+}
+
+fn subscribe_message(events: HashSet<String>) -> String {
+    subscription_message("subscribe", events)
+}
+
+fn subscription_message(call: &str, events: HashSet<String>) -> String {
+    let mut message = Map::new();
+    message.insert("call".into(), call.into());
+    message.insert("events".into(), events.into_iter().collect::<Vec<String>>().into());
+    Value::Object(message).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use super::*;
+
+    const TIMEOUT: Duration = Duration::from_secs(10);
+
+    /// Plays the OverlayPlugin server, records the messages it receives and answers the calls
+    struct StandIn {
+        server: soup::Server,
+        connections: Rc<RefCell<Vec<soup::WebsocketConnection>>>,
+        received: Rc<RefCell<Vec<Value>>>
+    }
+
+    impl StandIn {
+        /// Listens on a port picked by the system, read back with `url`
+        fn start() -> Self {
+            let server: soup::Server = glib::Object::new(&[]);
+            server.listen_local(0, soup::ServerListenOptions::IPV4_ONLY).expect("Could not start the stand-in server");
+
+            let connections: Rc<RefCell<Vec<soup::WebsocketConnection>>> = Rc::default();
+            let received: Rc<RefCell<Vec<Value>>> = Rc::default();
+            let (connections_cloned, received_cloned) = (connections.clone(), received.clone());
+            server.add_websocket_handler(Some(RELAY_PATH), None, &[], move |_, _, _, connection| {
+                connections_cloned.borrow_mut().push(connection.clone());
+
+                let received = received_cloned.clone();
+                connection.connect_message(move |connection, data_type, data| {
+                    let Some(message) = text_message(data_type, data).and_then(|text| serde_json::from_str::<Value>(text).ok()) else {
+                        return;
+                    };
+                    if let Some(rseq) = message.get("rseq") {
+                        // The combatants asked for by id are white mages
+                        let combatants: Vec<Value> = message
+                            .get("ids")
+                            .and_then(Value::as_array)
+                            .into_iter()
+                            .flatten()
+                            .map(|id| {
+                                let mut combatant = Map::new();
+                                combatant.insert("ID".into(), id.clone());
+                                combatant.insert("Job".into(), 24.into());
+                                Value::Object(combatant)
+                            })
+                            .collect();
+                        let mut response = Map::new();
+                        response.insert("rseq".into(), rseq.clone());
+                        response.insert("combatants".into(), Value::Array(combatants));
+                        connection.send_text(&Value::Object(response).to_string());
+                    }
+                    received.borrow_mut().push(message);
+                });
+            });
+
+            Self { server, connections, received }
+        }
+
+        fn url(&self) -> String {
+            let port = listening_port(&self.server).expect("The stand-in server has no port");
+            format!("ws://127.0.0.1:{}{}", port, RELAY_PATH)
+        }
+
+        fn send(&self, text: &str) {
+            let connection = self.connections.borrow().last().cloned().expect("The relay is not connected");
+            connection.send_text(text);
+        }
+
+        fn subscribed_events(&self) -> HashSet<String> {
+            self.received
+                .borrow()
+                .iter()
+                .filter(|message| message.get("call").and_then(Value::as_str) == Some("subscribe"))
+                .filter_map(|message| message.get("events").and_then(Value::as_array))
+                .flatten()
+                .filter_map(Value::as_str)
+                .map(String::from)
+                .collect()
+        }
+
+        fn calls(&self, call: &str) -> Vec<Value> {
+            self.received
+                .borrow()
+                .iter()
+                .filter(|message| message.get("call").and_then(Value::as_str) == Some(call))
+                .cloned()
+                .collect()
+        }
+    }
+
+    /// An overlay connected to the relay
+    struct TestClient {
+        _session: soup::Session,
+        connection: soup::WebsocketConnection,
+        received: Rc<RefCell<Vec<Value>>>
+    }
+
+    impl TestClient {
+        fn connect(url: &str) -> Self {
+            let session = soup::Session::new();
+            let message = soup::Message::new("GET", url).expect("Invalid relay url");
+            let result: Rc<RefCell<Option<soup::WebsocketConnection>>> = Rc::default();
+            let result_cloned = result.clone();
+            session.websocket_connect_async(
+                &message,
+                None,
+                &[],
+                glib::Priority::default(),
+                None::<&gio::Cancellable>,
+                move |connection| {
+                    *result_cloned.borrow_mut() = Some(connection.expect("Could not connect to the relay"));
+                }
+            );
+            wait_until("the overlay to connect to the relay", || result.borrow().is_some());
+            let connection = result.borrow_mut().take().unwrap();
+
+            let received: Rc<RefCell<Vec<Value>>> = Rc::default();
+            let received_cloned = received.clone();
+            connection.connect_message(move |_, data_type, data| {
+                if let Some(message) = text_message(data_type, data).and_then(|text| serde_json::from_str::<Value>(text).ok()) {
+                    received_cloned.borrow_mut().push(message);
+                }
+            });
+
+            Self { _session: session, connection, received }
+        }
+
+        fn count(&self, key: &str, value: &Value) -> usize {
+            self.received.borrow().iter().filter(|message| message.get(key) == Some(value)).count()
+        }
+    }
+
+    fn wait_until(description: &str, condition: impl Fn() -> bool) {
+        let context = glib::MainContext::default();
+        let deadline = Instant::now() + TIMEOUT;
+        while !condition() {
+            assert!(Instant::now() < deadline, "Timed out waiting for {}", description);
+            if !context.iteration(false) {
+                std::thread::sleep(Duration::from_millis(5));
+            }
+        }
+    }
+
+    #[test]
+    fn relays_a_stand_in_server() {
+        let context = glib::MainContext::default();
+        let _guard = context.acquire().expect("The main context is used by another test");
+
+        let stand_in = StandIn::start();
+        let (event_sender, event_receiver) = async_channel::unbounded();
+        // Both servers listen on ports picked by the system, the tests can run side by side
+        let app_config = AppConfig::with_relay(&stand_in.url(), 0);
+        let relay = Relay::start(&app_config, event_sender).expect("Could not start the relay");
+        wait_until("the relay to connect upstream", || stand_in.connections.borrow().len() == 1);
+
+        let subscribed_client = TestClient::connect(relay.local_url());
+        let other_client = TestClient::connect(relay.local_url());
+        subscribed_client.connection.send_text(r#"{"call":"subscribe","events":["LogLine"]}"#);
+        wait_until("the subscription upstream", || stand_in.subscribed_events().contains("LogLine"));
+
+        // The events only go to the subscribed overlays
+        stand_in.send(r#"{"type":"LogLine","line":["00","text"]}"#);
+        wait_until("the event", || subscribed_client.count("type", &"LogLine".into()) == 1);
+        assert_eq!(other_client.count("type", &"LogLine".into()), 0);
+
+        // Both overlays use the same rseq, the relay gives the calls distinct ones upstream
+        subscribed_client.connection.send_text(r#"{"call":"getCombatants","rseq":1}"#);
+        other_client.connection.send_text(r#"{"call":"getCombatants","rseq":1}"#);
+        wait_until("the call responses", || {
+            subscribed_client.count("rseq", &1.into()) == 1 && other_client.count("rseq", &1.into()) == 1
+        });
+        let upstream_rseqs: HashSet<u64> = stand_in
+            .calls("getCombatants")
+            .iter()
+            .filter_map(|call| call.get("rseq").and_then(Value::as_u64))
+            .collect();
+        assert_eq!(upstream_rseqs.len(), 2);
+
+        // The job of a new player is asked for, and not sent to the overlays
+        stand_in.send(r#"{"type":"ChangePrimaryPlayer","charID":275,"charName":"Player"}"#);
+        let job = RefCell::new(None);
+        wait_until("the job of the player", || {
+            while let Ok(action) = event_receiver.try_recv() {
+                if let AppAction::GameStateChanged(game_state) = action {
+                    *job.borrow_mut() = game_state.job;
+                }
+            }
+            job.borrow().is_some()
+        });
+        assert_eq!(job.borrow().as_deref(), Some("WHM"));
+        assert_eq!(subscribed_client.received.borrow().len(), 2);
+
+        // The subscriptions are sent again to the new connection
+        stand_in.received.borrow_mut().clear();
+        stand_in.connections.borrow()[0].close(1000, None);
+        wait_until("the relay to reconnect", || stand_in.connections.borrow().len() == 2);
+        wait_until("the subscriptions to be replayed", || stand_in.subscribed_events().contains("LogLine"));
+        for event in GAME_STATE_EVENTS {
+            assert!(stand_in.subscribed_events().contains(event), "{} is not subscribed again", event);
+        }
+
+        // An overlay subscribing after a synthetic encounter started still gets its zone and party
+        relay.start_synthetic(SyntheticConfig::default());
+        let late_client = TestClient::connect(relay.local_url());
+        late_client.connection.send_text(r#"{"call":"subscribe","events":["ChangeZone","PartyChanged"]}"#);
+        wait_until("the zone and party of the encounter", || {
+            late_client.count("type", &"ChangeZone".into()) == 1 && late_client.count("type", &"PartyChanged".into()) == 1
+        });
+        assert_eq!(late_client.count("type", &"ChangePrimaryPlayer".into()), 0);
+    }
+
+    #[test]
+    fn points_the_websocket_of_an_overlay_to_the_relay() {
+        let local_url = "ws://127.0.0.1:10511/ws";
+
+        assert_eq!(
+            relayed_url("https://example.com/?OVERLAY_WS=ws://127.0.0.1:10501/ws&lang=en", local_url),
+            "https://example.com/?OVERLAY_WS=ws://127.0.0.1:10511/ws&lang=en"
+        );
+        assert_eq!(
+            relayed_url("https://example.com/?lang=en&OVERLAY_WS=ws://host/ws#top", local_url),
+            "https://example.com/?lang=en&OVERLAY_WS=ws://127.0.0.1:10511/ws#top"
+        );
+    }
+
+    #[test]
+    fn adds_the_websocket_to_the_overlays_without_one() {
+        let local_url = "ws://127.0.0.1:10511/ws";
+
+        assert_eq!(
+            relayed_url("https://example.com/overlay/", local_url),
+            "https://example.com/overlay/?OVERLAY_WS=ws://127.0.0.1:10511/ws"
+        );
+        assert_eq!(
+            relayed_url("https://example.com/overlay/?lang=en#top", local_url),
+            "https://example.com/overlay/?lang=en&OVERLAY_WS=ws://127.0.0.1:10511/ws#top"
+        );
+        assert_eq!(
+            relayed_url("file:///home/user/overlay.html?", local_url),
+            "file:///home/user/overlay.html?OVERLAY_WS=ws://127.0.0.1:10511/ws"
+        );
+    }
+
+    #[test]
+    fn replaces_the_legacy_websocket_parameter() {
+        let local_url = "ws://127.0.0.1:10511/ws";
+
+        assert_eq!(
+            relayed_url("https://hibiyasleep.github.io/kagerou/overlay/?HOST_PORT=ws://127.0.0.1:10501/&lang=ko", local_url),
+            "https://hibiyasleep.github.io/kagerou/overlay/?OVERLAY_WS=ws://127.0.0.1:10511/ws&lang=ko"
+        );
+        // Only one websocket parameter is left when both are there
+        assert_eq!(
+            relayed_url("https://example.com/?HOST_PORT=ws://127.0.0.1:10501/&OVERLAY_WS=ws://127.0.0.1:10501/ws", local_url),
+            "https://example.com/?OVERLAY_WS=ws://127.0.0.1:10511/ws"
+        );
+    }
+}
@@ -0,0 +1,118 @@
+use std::fs::{File, OpenOptions};
+use std::io::ErrorKind;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+/// The events captured while recording, they are subscribed upstream for the whole recording
+pub const RECORDED_EVENTS: [&str; 8] = [
+    "CombatData",
+    "LogLine",
+    "ChangeZone",
+    "ChangePrimaryPlayer",
+    "PartyChanged",
+    "InCombat",
+    "OnlineStatusChanged",
+    "EnmityTargetData",
+];
+
+pub const RECORDING_EXTENSION: &str = "xivrec";
+
+/// Writes the upstream events to a file, one event per line prefixed by
+/// the milliseconds elapsed since the beginning of the recording.
+/// Every event is flushed, a crash only loses the event being written.
+pub struct Recorder {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    started_at: Instant
+}
+
+impl Recorder {
+    pub fn create(recordings_path: &Path) -> Result<Recorder, std::io::Error> {
+        std::fs::create_dir_all(recordings_path)?;
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_millis())
+            .unwrap_or_default();
+        let (path, file) = Recorder::create_new_file(recordings_path, &format!("recording-{}", timestamp))?;
+
+        Ok(Recorder {
+            writer: BufWriter::new(file),
+            path,
+            started_at: Instant::now()
+        })
+    }
+
+    /// An existing recording is never overwritten, a number is added to the name instead
+    fn create_new_file(recordings_path: &Path, name: &str) -> Result<(PathBuf, File), std::io::Error> {
+        let mut suffix = 1;
+        loop {
+            let file_name = match suffix {
+                1 => format!("{}.{}", name, RECORDING_EXTENSION),
+                _ => format!("{}-{}.{}", name, suffix, RECORDING_EXTENSION)
+            };
+            let path = recordings_path.join(file_name);
+
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(file) => return Ok((path, file)),
+                Err(error) if error.kind() == ErrorKind::AlreadyExists => suffix += 1,
+                Err(error) => return Err(error)
+            }
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn record(&mut self, text: &str) -> Result<(), std::io::Error> {
+        let elapsed = self.started_at.elapsed().as_millis();
+        // OverlayPlugin sends compact json, so an event never spans multiple lines
+        writeln!(self.writer, "{}\t{}", elapsed, text.replace('\n', " "))?;
+        self.writer.flush()
+    }
+
+    pub fn finish(mut self) -> Result<PathBuf, std::io::Error> {
+        self.writer.flush()?;
+        Ok(self.path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recordings_path(test_name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("xivoverlay-{}-{}", test_name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&path);
+        path
+    }
+
+    #[test]
+    fn an_existing_recording_is_not_overwritten() {
+        let path = recordings_path("recorder-names");
+        std::fs::create_dir_all(&path).unwrap();
+        std::fs::write(path.join(format!("recording-1.{}", RECORDING_EXTENSION)), "kept").unwrap();
+
+        let (first_path, _) = Recorder::create_new_file(&path, "recording-1").unwrap();
+        let (second_path, _) = Recorder::create_new_file(&path, "recording-1").unwrap();
+        assert_eq!(first_path, path.join(format!("recording-1-2.{}", RECORDING_EXTENSION)));
+        assert_eq!(second_path, path.join(format!("recording-1-3.{}", RECORDING_EXTENSION)));
+        assert_eq!(std::fs::read_to_string(path.join(format!("recording-1.{}", RECORDING_EXTENSION))).unwrap(), "kept");
+
+        let _ = std::fs::remove_dir_all(&path);
+    }
+
+    #[test]
+    fn the_events_are_written_before_the_recording_is_finished() {
+        let path = recordings_path("recorder-flush");
+        let mut recorder = Recorder::create(&path).unwrap();
+        recorder.record(r#"{"type":"LogLine"}"#).unwrap();
+
+        let content = std::fs::read_to_string(recorder.path()).unwrap();
+        assert!(content.ends_with("\t{\"type\":\"LogLine\"}\n"), "{:?}", content);
+
+        let _ = std::fs::remove_dir_all(&path);
+    }
+}
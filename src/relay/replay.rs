@@ -0,0 +1,243 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use serde_json::Value;
+
+
+pub const REPLAY_SPEEDS: [f64; 4] = [1.0, 2.0, 4.0, 8.0];
+
+pub struct RecordedEvent {
+    pub at: Duration,
+    pub event_type: String,
+    pub text: String
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReplayStatus {
+    pub position: Duration,
+    pub duration: Duration,
+    pub is_paused: bool,
+    pub speed: f64
+}
+
+/// Plays a recording back, the position advances with the wall clock multiplied by the speed
+pub struct Replay {
+    events: Vec<RecordedEvent>,
+    next_index: usize,
+    position: Duration,
+    resumed_at: Option<Instant>,
+    speed: f64
+}
+
+impl Replay {
+    pub fn load(path: &Path) -> Result<Replay, std::io::Error> {
+        let content = std::fs::read_to_string(path)?;
+
+        let mut events = Vec::new();
+        for line in content.lines() {
+            let Some((at, text)) = line.split_once('\t') else {
+                continue;
+            };
+            let (Ok(at), Ok(message)) = (at.parse::<u64>(), serde_json::from_str::<Value>(text)) else {
+                warn!("Skipping an invalid line in the recording {:?}", path);
+                continue;
+            };
+            if let Some(event_type) = message.get("type").and_then(Value::as_str) {
+                events.push(RecordedEvent {
+                    at: Duration::from_millis(at),
+                    event_type: event_type.to_string(),
+                    text: text.to_string()
+                });
+            }
+        }
+        Ok(Replay::new(events))
+    }
+
+    fn new(mut events: Vec<RecordedEvent>) -> Replay {
+        events.sort_by_key(|event| event.at);
+
+        Replay {
+            events,
+            next_index: 0,
+            position: Duration::ZERO,
+            resumed_at: Some(Instant::now()),
+            speed: 1.0
+        }
+    }
+
+    pub fn status(&self) -> ReplayStatus {
+        ReplayStatus {
+            position: self.position(),
+            duration: self.duration(),
+            is_paused: self.resumed_at.is_none(),
+            speed: self.speed
+        }
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.events.last().map(|event| event.at).unwrap_or_default()
+    }
+
+    pub fn position(&self) -> Duration {
+        let elapsed = self.resumed_at
+            .map(|resumed_at| resumed_at.elapsed().mul_f64(self.speed))
+            .unwrap_or_default();
+        (self.position + elapsed).min(self.duration())
+    }
+
+    pub fn set_paused(&mut self, is_paused: bool) {
+        if is_paused {
+            self.position = self.position();
+            self.resumed_at = None;
+        } else if self.resumed_at.is_none() {
+            // Playing from the end starts the recording over
+            if self.position >= self.duration() {
+                self.seek(Duration::ZERO);
+            }
+            self.resumed_at = Some(Instant::now());
+        }
+    }
+
+    pub fn set_speed(&mut self, speed: f64) {
+        self.position = self.position();
+        if self.resumed_at.is_some() {
+            self.resumed_at = Some(Instant::now());
+        }
+        self.speed = speed;
+    }
+
+    /// Move to the given position and return the last event of each type before it,
+    /// so that the overlays can display the state of the encounter right away
+    pub fn seek(&mut self, position: Duration) -> Vec<&RecordedEvent> {
+        self.position = position.min(self.duration());
+        if self.resumed_at.is_some() {
+            self.resumed_at = Some(Instant::now());
+        }
+        self.next_index = self.events.partition_point(|event| event.at < self.position);
+
+        let mut last_events: HashMap<&str, &RecordedEvent> = HashMap::new();
+        for event in self.events[..self.next_index].iter() {
+            // Log lines are a stream, replaying only the last one would not make sense
+            if event.event_type != "LogLine" {
+                last_events.insert(&event.event_type, event);
+            }
+        }
+        let mut last_events: Vec<&RecordedEvent> = last_events.into_values().collect();
+        last_events.sort_by_key(|event| event.at);
+        last_events
+    }
+
+    /// Return the events that should have been played by now, pausing at the end of the recording
+    pub fn due_events(&mut self) -> &[RecordedEvent] {
+        let position = self.position();
+        let first_index = self.next_index;
+        self.next_index += self.events[first_index..].partition_point(|event| event.at <= position);
+
+        if self.next_index >= self.events.len() {
+            self.set_paused(true);
+        }
+
+        &self.events[first_index..self.next_index]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(at_millis: u64, event_type: &str) -> RecordedEvent {
+        RecordedEvent {
+            at: Duration::from_millis(at_millis),
+            event_type: event_type.to_string(),
+            text: format!("{{\"type\":\"{}\",\"at\":{}}}", event_type, at_millis)
+        }
+    }
+
+    /// Paused at the start, the position only moves with the seeks
+    fn paused_replay() -> Replay {
+        let mut replay = Replay::new(vec![
+            event(3000, "CombatData"),
+            event(1000, "ChangeZone"),
+            event(1000, "CombatData"),
+            event(2000, "LogLine"),
+            event(2000, "CombatData"),
+            event(4000, "LogLine")
+        ]);
+        replay.set_paused(true);
+        replay.seek(Duration::ZERO);
+        replay
+    }
+
+    fn times(events: &[&RecordedEvent]) -> Vec<(u128, String)> {
+        events.iter().map(|event| (event.at.as_millis(), event.event_type.clone())).collect()
+    }
+
+    #[test]
+    fn seeking_returns_the_last_event_of_each_type() {
+        let mut replay = paused_replay();
+
+        let events = replay.seek(Duration::from_millis(2500));
+        // The log lines are not replayed, only the last combat data is
+        assert_eq!(times(&events), vec![(1000, "ChangeZone".to_string()), (2000, "CombatData".to_string())]);
+    }
+
+    #[test]
+    fn seeking_backwards_and_forwards_moves_the_next_events() {
+        let mut replay = paused_replay();
+
+        replay.seek(Duration::from_millis(3500));
+        assert_eq!(replay.position(), Duration::from_millis(3500));
+        assert!(replay.due_events().is_empty());
+
+        let events = replay.seek(Duration::from_millis(1500));
+        assert_eq!(times(&events), vec![(1000, "ChangeZone".to_string()), (1000, "CombatData".to_string())]);
+        assert!(replay.due_events().is_empty());
+
+        // The events at the position are due, the following ones are not
+        replay.seek(Duration::from_millis(2000));
+        let due: Vec<&RecordedEvent> = replay.due_events().iter().collect();
+        assert_eq!(times(&due), vec![(2000, "LogLine".to_string()), (2000, "CombatData".to_string())]);
+        assert!(replay.due_events().is_empty());
+
+        // Past the end, the position stops at the last event
+        replay.seek(Duration::from_secs(60));
+        assert_eq!(replay.position(), replay.duration());
+    }
+
+    #[test]
+    fn the_last_events_pause_the_replay() {
+        let mut replay = paused_replay();
+        replay.seek(Duration::from_millis(3500));
+        replay.set_paused(false);
+
+        replay.seek(Duration::from_millis(4000));
+        assert_eq!(replay.due_events().len(), 1);
+        assert!(replay.status().is_paused);
+
+        // Playing from the end starts over
+        replay.set_paused(false);
+        assert!(replay.position() < Duration::from_millis(1000));
+    }
+
+    #[test]
+    fn the_speed_multiplies_the_elapsed_time() {
+        let mut replay = paused_replay();
+        replay.seek(Duration::from_millis(1000));
+
+        // Changing the speed keeps the position
+        replay.set_speed(4.0);
+        assert_eq!(replay.position(), Duration::from_millis(1000));
+        assert_eq!(replay.status().speed, 4.0);
+
+        replay.set_paused(false);
+        std::thread::sleep(Duration::from_millis(100));
+        replay.set_paused(true);
+        assert!(replay.position() >= Duration::from_millis(1400));
+
+        // The time played at the previous speed is kept
+        let position = replay.position();
+        replay.set_speed(1.0);
+        assert_eq!(replay.position(), position);
+    }
+}
@@ -0,0 +1,424 @@
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use serde_json::{Map, Value};
+
+
+const COMBAT_DATA_INTERVAL: Duration = Duration::from_secs(1);
+const PAUSE_BETWEEN_ENCOUNTERS: Duration = Duration::from_secs(10);
+const PRIMARY_PLAYER_ID: u32 = 0x10000001;
+
+const NAMES: [&str; 8] = [
+    "Aria Moonfall",
+    "Bram Ironhide",
+    "Cyrielle Dawn",
+    "Dorian Vale",
+    "Elys Thornwood",
+    "Faro Quickstep",
+    "Gwen Ashcroft",
+    "Hiro Tanager",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Role {
+    Tank,
+    Healer,
+    Dps
+}
+
+/// Job abbreviation, OverlayPlugin job id and role
+const JOBS: [(&str, u32, Role); 21] = [
+    ("PLD", 19, Role::Tank),
+    ("MNK", 20, Role::Dps),
+    ("WAR", 21, Role::Tank),
+    ("DRG", 22, Role::Dps),
+    ("BRD", 23, Role::Dps),
+    ("WHM", 24, Role::Healer),
+    ("BLM", 25, Role::Dps),
+    ("SMN", 27, Role::Dps),
+    ("SCH", 28, Role::Healer),
+    ("NIN", 30, Role::Dps),
+    ("MCH", 31, Role::Dps),
+    ("DRK", 32, Role::Tank),
+    ("AST", 33, Role::Healer),
+    ("SAM", 34, Role::Dps),
+    ("RDM", 35, Role::Dps),
+    ("GNB", 37, Role::Tank),
+    ("DNC", 38, Role::Dps),
+    ("RPR", 39, Role::Dps),
+    ("SGE", 40, Role::Healer),
+    ("VPR", 41, Role::Dps),
+    ("PCT", 42, Role::Dps),
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyntheticConfig {
+    /// Job abbreviations of the party members, the first one is the player
+    pub party: Vec<String>,
+    pub encounter_length: Duration,
+    /// Average dps of a dps job, tanks and healers deal less
+    pub base_dps: f64,
+    /// How far from the average the members can be, between 0 and 1
+    pub dps_spread: f64
+}
+
+impl Default for SyntheticConfig {
+    fn default() -> Self {
+        Self {
+            party: ["PLD", "WAR", "WHM", "SCH", "DRG", "NIN", "BRD", "BLM"]
+                .iter()
+                .map(|job| job.to_string())
+                .collect(),
+            encounter_length: Duration::from_secs(300),
+            base_dps: 15000.0,
+            dps_spread: 0.25
+        }
+    }
+}
+
+impl SyntheticConfig {
+    pub fn is_known_job(job: &str) -> bool {
+        job_infos(job).is_some()
+    }
+}
+
+struct SyntheticCombatant {
+    name: String,
+    job: &'static str,
+    job_id: u32,
+    role: Role,
+    dps: f64,
+    damage: f64,
+    healed: f64,
+    max_hit: u64,
+    crit_rate: f64,
+    direct_hit_rate: f64
+}
+
+/// Generates an endless series of encounters with a fake party
+pub struct SyntheticEncounter {
+    config: SyntheticConfig,
+    combatants: Vec<SyntheticCombatant>,
+    random_state: u64,
+    started_at: Instant,
+    last_combat_data: Instant,
+    in_combat: bool
+}
+
+impl SyntheticEncounter {
+    pub fn new(config: SyntheticConfig) -> Self {
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_nanos() as u64)
+            .unwrap_or(0x2545F4914F6CDD1D);
+
+        let combatants = config.party
+            .iter()
+            .filter_map(|job| job_infos(job))
+            .enumerate()
+            .map(|(index, (job, job_id, role))| SyntheticCombatant {
+                name: combatant_name(index),
+                job,
+                job_id,
+                role,
+                dps: 0.0,
+                damage: 0.0,
+                healed: 0.0,
+                max_hit: 0,
+                crit_rate: 0.0,
+                direct_hit_rate: 0.0
+            })
+            .collect();
+
+        let mut encounter = Self {
+            config,
+            combatants,
+            // Xorshift does not work with a zero state
+            random_state: seed | 1,
+            // The first encounter starts right away
+            started_at: Instant::now().checked_sub(PAUSE_BETWEEN_ENCOUNTERS).unwrap_or_else(Instant::now),
+            last_combat_data: Instant::now(),
+            in_combat: false
+        };
+        encounter.reset_combatants();
+        encounter
+    }
+
+    /// The events describing the zone and the party, sent once when the generator starts
+    pub fn start_events(&self) -> Vec<(String, String)> {
+        let mut change_zone = Map::new();
+        change_zone.insert("zoneID".into(), 0.into());
+        change_zone.insert("zoneName".into(), "Synthetic encounter".into());
+
+        let mut change_primary_player = Map::new();
+        change_primary_player.insert("charID".into(), PRIMARY_PLAYER_ID.into());
+        change_primary_player.insert("charName".into(), self.combatants.first().map(|combatant| combatant.name.clone()).unwrap_or_default().into());
+
+        let party: Vec<Value> = self.combatants
+            .iter()
+            .enumerate()
+            .map(|(index, combatant)| {
+                let mut member = Map::new();
+                member.insert("id".into(), format!("{:X}", PRIMARY_PLAYER_ID + index as u32).into());
+                member.insert("name".into(), combatant.name.clone().into());
+                member.insert("worldId".into(), 0.into());
+                member.insert("job".into(), combatant.job_id.into());
+                member.insert("inParty".into(), true.into());
+                Value::Object(member)
+            })
+            .collect();
+        let mut party_changed = Map::new();
+        party_changed.insert("party".into(), party.into());
+
+        vec![
+            event("ChangeZone", change_zone),
+            event("ChangePrimaryPlayer", change_primary_player),
+            event("PartyChanged", party_changed),
+        ]
+    }
+
+    /// Advance the encounter and return the events generated since the last call
+    pub fn tick(&mut self) -> Vec<(String, String)> {
+        let mut events = Vec::new();
+        let elapsed = self.started_at.elapsed();
+
+        if !self.in_combat {
+            if elapsed >= PAUSE_BETWEEN_ENCOUNTERS {
+                self.started_at = Instant::now();
+                self.last_combat_data = self.started_at;
+                self.in_combat = true;
+                self.reset_combatants();
+                events.push(in_combat_event(true));
+            }
+            return events;
+        }
+
+        if self.last_combat_data.elapsed() >= COMBAT_DATA_INTERVAL {
+            let interval = self.last_combat_data.elapsed().as_secs_f64();
+            self.last_combat_data = Instant::now();
+            self.advance_combatants(interval);
+
+            let is_over = elapsed >= self.config.encounter_length;
+            events.push(self.combat_data_event(elapsed.min(self.config.encounter_length), !is_over));
+
+            if is_over {
+                self.started_at = Instant::now();
+                self.in_combat = false;
+                events.push(in_combat_event(false));
+            }
+        }
+
+        events
+    }
+
+    fn reset_combatants(&mut self) {
+        let base_dps = self.config.base_dps;
+        let spread = self.config.dps_spread.clamp(0.0, 1.0);
+
+        for index in 0..self.combatants.len() {
+            let variation = 1.0 + spread * (self.next_random() * 2.0 - 1.0);
+            let crit_rate = 0.15 + self.next_random() * 0.15;
+            let direct_hit_rate = 0.2 + self.next_random() * 0.2;
+
+            let combatant = &mut self.combatants[index];
+            let role_factor = match combatant.role {
+                Role::Tank => 0.75,
+                Role::Healer => 0.6,
+                Role::Dps => 1.0
+            };
+            combatant.dps = base_dps * role_factor * variation;
+            combatant.damage = 0.0;
+            combatant.healed = 0.0;
+            combatant.max_hit = 0;
+            combatant.crit_rate = crit_rate;
+            combatant.direct_hit_rate = direct_hit_rate;
+        }
+    }
+
+    fn advance_combatants(&mut self, interval: f64) {
+        for index in 0..self.combatants.len() {
+            // Damage comes in bursts, not as a flat line
+            let burst = 0.5 + self.next_random();
+            let hit_variation = 1.0 + self.next_random() * 2.0;
+
+            let combatant = &mut self.combatants[index];
+            let damage = combatant.dps * interval * burst;
+            combatant.damage += damage;
+            combatant.max_hit = combatant.max_hit.max((damage * 0.3 * hit_variation) as u64);
+            if combatant.role == Role::Healer {
+                combatant.healed += combatant.dps * 1.5 * interval * burst;
+            }
+        }
+    }
+
+    fn combat_data_event(&self, duration: Duration, is_active: bool) -> (String, String) {
+        let seconds = duration.as_secs_f64().max(1.0);
+        let total_damage: f64 = self.combatants.iter().map(|combatant| combatant.damage).sum();
+        let total_healed: f64 = self.combatants.iter().map(|combatant| combatant.healed).sum();
+        let formatted_duration = format!("{:02}:{:02}", duration.as_secs() / 60, duration.as_secs() % 60);
+
+        let mut encounter = Map::new();
+        encounter.insert("title".into(), "Striking Dummy".into());
+        encounter.insert("CurrentZoneName".into(), "Synthetic encounter".into());
+        encounter.insert("duration".into(), formatted_duration.clone().into());
+        encounter.insert("DURATION".into(), duration.as_secs().to_string().into());
+        encounter.insert("damage".into(), format!("{:.0}", total_damage).into());
+        encounter.insert("encdps".into(), format!("{:.2}", total_damage / seconds).into());
+        encounter.insert("ENCDPS".into(), format!("{:.0}", total_damage / seconds).into());
+        encounter.insert("healed".into(), format!("{:.0}", total_healed).into());
+        encounter.insert("enchps".into(), format!("{:.2}", total_healed / seconds).into());
+        encounter.insert("ENCHPS".into(), format!("{:.0}", total_healed / seconds).into());
+
+        let mut combatants = Map::new();
+        for (index, combatant) in self.combatants.iter().enumerate() {
+            // ACT names the player YOU by default
+            let name = if index == 0 { "YOU".to_string() } else { combatant.name.clone() };
+            let percent = |value: f64, total: f64| {
+                if total > 0.0 { format!("{:.0}%", value / total * 100.0) } else { "0%".to_string() }
+            };
+
+            let mut entry = Map::new();
+            entry.insert("name".into(), name.clone().into());
+            entry.insert("Job".into(), capitalize(combatant.job).into());
+            entry.insert("duration".into(), formatted_duration.clone().into());
+            entry.insert("DURATION".into(), duration.as_secs().to_string().into());
+            entry.insert("damage".into(), format!("{:.0}", combatant.damage).into());
+            entry.insert("damage%".into(), percent(combatant.damage, total_damage).into());
+            entry.insert("encdps".into(), format!("{:.2}", combatant.damage / seconds).into());
+            entry.insert("ENCDPS".into(), format!("{:.0}", combatant.damage / seconds).into());
+            entry.insert("healed".into(), format!("{:.0}", combatant.healed).into());
+            entry.insert("healed%".into(), percent(combatant.healed, total_healed).into());
+            entry.insert("enchps".into(), format!("{:.2}", combatant.healed / seconds).into());
+            entry.insert("ENCHPS".into(), format!("{:.0}", combatant.healed / seconds).into());
+            entry.insert("OverHealPct".into(), "0%".into());
+            entry.insert("crithit%".into(), format!("{:.0}%", combatant.crit_rate * 100.0).into());
+            entry.insert("DirectHitPct".into(), format!("{:.0}%", combatant.direct_hit_rate * 100.0).into());
+            entry.insert("deaths".into(), "0".into());
+            entry.insert("maxhit".into(), format!("Attack-{}", combatant.max_hit).into());
+            entry.insert("MAXHIT".into(), combatant.max_hit.to_string().into());
+            combatants.insert(name, Value::Object(entry));
+        }
+
+        let mut combat_data = Map::new();
+        combat_data.insert("Encounter".into(), Value::Object(encounter));
+        combat_data.insert("Combatant".into(), Value::Object(combatants));
+        combat_data.insert("isActive".into(), is_active.to_string().into());
+
+        event("CombatData", combat_data)
+    }
+
+    /// Xorshift, a uniform number between 0 and 1
+    fn next_random(&mut self) -> f64 {
+        self.random_state ^= self.random_state << 13;
+        self.random_state ^= self.random_state >> 7;
+        self.random_state ^= self.random_state << 17;
+        (self.random_state >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// The names go around again with a number past the eighth member, the combatants are keyed by name
+fn combatant_name(index: usize) -> String {
+    let name = NAMES[index % NAMES.len()];
+    match index / NAMES.len() {
+        0 => name.to_string(),
+        round => format!("{} {}", name, round + 1)
+    }
+}
+
+fn job_infos(job: &str) -> Option<(&'static str, u32, Role)> {
+    JOBS.iter()
+        .find(|(abbreviation, _, _)| abbreviation.eq_ignore_ascii_case(job.trim()))
+        .copied()
+}
+
+fn capitalize(job: &str) -> String {
+    let lowercase = job.to_lowercase();
+    let mut characters = lowercase.chars();
+    match characters.next() {
+        Some(first) => first.to_uppercase().chain(characters).collect(),
+        None => String::new()
+    }
+}
+
+fn in_combat_event(in_combat: bool) -> (String, String) {
+    let mut in_combat_message = Map::new();
+    in_combat_message.insert("inACTCombat".into(), in_combat.into());
+    in_combat_message.insert("inGameCombat".into(), in_combat.into());
+    event("InCombat", in_combat_message)
+}
+
+fn event(event_type: &str, mut message: Map<String, Value>) -> (String, String) {
+    message.insert("type".into(), event_type.into());
+    (event_type.to_string(), Value::Object(message).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    #[test]
+    fn the_combatants_of_a_large_party_have_unique_names() {
+        let config = SyntheticConfig {
+            party: vec!["WHM".to_string(); 24],
+            ..Default::default()
+        };
+        let encounter = SyntheticEncounter::new(config);
+
+        let names: HashSet<&str> = encounter.combatants.iter().map(|combatant| combatant.name.as_str()).collect();
+        assert_eq!(names.len(), 24);
+        assert!(names.contains("Aria Moonfall"));
+        assert!(names.contains("Aria Moonfall 2"));
+        assert!(names.contains("Hiro Tanager 3"));
+    }
+
+    #[test]
+    fn the_combat_data_has_the_shape_of_act() {
+        let mut encounter = SyntheticEncounter::new(SyntheticConfig::default());
+        encounter.advance_combatants(1.0);
+
+        let (event_type, text) = encounter.combat_data_event(Duration::from_secs(75), true);
+        let message = serde_json::from_str::<Value>(&text).unwrap();
+        assert_eq!(event_type, "CombatData");
+        assert_eq!(message["type"], "CombatData");
+        assert_eq!(message["isActive"], "true");
+        assert_eq!(message["Encounter"]["duration"], "01:15");
+        assert_eq!(message["Encounter"]["DURATION"], "75");
+
+        let combatants = message["Combatant"].as_object().unwrap();
+        assert_eq!(combatants.len(), 8);
+        // The player is YOU, the others are keyed by their name
+        assert_eq!(combatants["YOU"]["Job"], "Pld");
+        assert_eq!(combatants["YOU"]["name"], "YOU");
+        assert_eq!(combatants["Bram Ironhide"]["Job"], "War");
+        for combatant in combatants.values() {
+            for field in ["encdps", "ENCDPS", "damage", "healed", "MAXHIT"] {
+                let value = combatant[field].as_str().unwrap_or_default();
+                assert!(value.parse::<f64>().is_ok(), "{} is not a number: {:?}", field, value);
+            }
+            assert!(combatant["damage%"].as_str().unwrap().ends_with('%'));
+        }
+    }
+
+    #[test]
+    fn the_dps_of_the_members_stays_within_the_spread() {
+        let config = SyntheticConfig {
+            party: vec!["PLD".to_string(), "WHM".to_string(), "BLM".to_string(), "DRG".to_string()],
+            base_dps: 10000.0,
+            dps_spread: 0.0,
+            ..Default::default()
+        };
+        let encounter = SyntheticEncounter::new(config.clone());
+        let dps: Vec<f64> = encounter.combatants.iter().map(|combatant| combatant.dps).collect();
+        assert_eq!(dps, vec![7500.0, 6000.0, 10000.0, 10000.0]);
+
+        let config = SyntheticConfig { party: vec!["BLM".to_string(); 50], dps_spread: 0.25, ..config };
+        for _ in 0..10 {
+            let mut encounter = SyntheticEncounter::new(config.clone());
+            encounter.reset_combatants();
+            for combatant in encounter.combatants.iter() {
+                assert!((7500.0..=12500.0).contains(&combatant.dps), "{} is out of the spread", combatant.dps);
+            }
+        }
+    }
+}
@@ -0,0 +1,87 @@
+use gtk::prelude::*;
+
+use crate::{catalog::{catalog_entries, CatalogEntry}, layout_config::LayoutConfig};
+
+/// Ask which overlay to start the new config from, `None` when cancelled
+pub fn pick_new_overlay(parent: &gtk::Window, websocket_url: &str) -> Option<LayoutConfig> {
+    let entries = catalog_entries();
+
+    let dialog = gtk::Dialog::builder()
+        .title("Add new config")
+        .transient_for(parent)
+        .modal(true)
+        .default_width(520)
+        .default_height(480)
+        .build();
+    dialog.add_buttons(&[("Cancel", gtk::ResponseType::Cancel), ("Create", gtk::ResponseType::Accept)]);
+    dialog.set_default_response(gtk::ResponseType::Accept);
+
+    let list_box = gtk::ListBox::new();
+    list_box.add(&create_row("Blank overlay", "Every setting is filled in by hand", None));
+    for entry in entries.iter() {
+        list_box.add(&create_row(&entry.name, &entry.description, Some(entry)));
+    }
+    list_box.select_row(list_box.row_at_index(0).as_ref());
+
+    let dialog_cloned = dialog.clone();
+    list_box.connect_row_activated(move |_, _| {
+        dialog_cloned.response(gtk::ResponseType::Accept);
+    });
+
+    let scrolled_window = gtk::ScrolledWindow::builder()
+        .hscrollbar_policy(gtk::PolicyType::Never)
+        .vexpand(true)
+        .margin(10)
+        .build();
+    scrolled_window.add(&list_box);
+    dialog.content_area().add(&scrolled_window);
+    dialog.show_all();
+
+    let response = dialog.run();
+    let selected_index = list_box.selected_row().map(|row| row.index());
+    dialog.close();
+
+    if response != gtk::ResponseType::Accept {
+        return None;
+    }
+
+    // The first row is the blank overlay
+    let overlay = match selected_index {
+        Some(index) if index > 0 => entries[index as usize - 1].to_layout_config(websocket_url),
+        _ => LayoutConfig::default()
+    };
+    Some(overlay)
+}
+
+fn create_row(name: &str, description: &str, entry: Option<&CatalogEntry>) -> gtk::ListBoxRow {
+    let row_box = gtk::Box::builder()
+        .orientation(gtk::Orientation::Vertical)
+        .spacing(2)
+        .margin(8)
+        .build();
+
+    let name_label = gtk::Label::builder().xalign(0.0).build();
+    name_label.set_markup(&format!("<b>{}</b>", glib::markup_escape_text(name)));
+    row_box.add(&name_label);
+
+    let description_label = gtk::Label::builder()
+        .label(description)
+        .xalign(0.0)
+        .wrap(true)
+        .build();
+    row_box.add(&description_label);
+
+    if let Some(entry) = entry {
+        let size_label = gtk::Label::builder()
+            .label(&format!("{} × {} px, {}", entry.width, entry.height, entry.url))
+            .xalign(0.0)
+            .ellipsize(gtk::pango::EllipsizeMode::End)
+            .build();
+        size_label.style_context().add_class("dim-label");
+        row_box.add(&size_label);
+    }
+
+    let row = gtk::ListBoxRow::new();
+    row.add(&row_box);
+    row
+}
@@ -0,0 +1,572 @@
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::rc::Rc;
+
+use async_channel::Sender;
+use gtk::prelude::*;
+
+use crate::app::AppAction;
+use crate::overlay::snapping::{snap, Bounds, Guide, SnapTargets};
+
+/// Space around the monitors on the canvas, in canvas pixels
+const CANVAS_MARGIN: f64 = 20.0;
+/// Size of the resize handle in the bottom right corner of the selected overlays
+const HANDLE_SIZE: f64 = 10.0;
+const NUDGE_STEP: i32 = 1;
+/// With Shift held
+const LARGE_NUDGE_STEP: i32 = 10;
+/// The overlays can not be resized below it, in screen pixels
+const MIN_SIZE: i32 = 20;
+
+pub struct EditorMonitor {
+    pub name: String,
+    pub bounds: Bounds
+}
+
+pub struct EditorOverlay {
+    pub name: String,
+    /// Absolute geometry, in screen pixels
+    pub bounds: Bounds,
+    pub is_active: bool
+}
+
+/// From screen coordinates to canvas coordinates
+#[derive(Clone, Copy)]
+struct Transform {
+    scale: f64,
+    offset_x: f64,
+    offset_y: f64
+}
+
+impl Transform {
+    fn to_canvas(self, bounds: Bounds) -> (f64, f64, f64, f64) {
+        (
+            self.offset_x + bounds.x as f64 * self.scale,
+            self.offset_y + bounds.y as f64 * self.scale,
+            bounds.width as f64 * self.scale,
+            bounds.height as f64 * self.scale
+        )
+    }
+
+    fn to_screen_distance(self, distance: f64) -> i32 {
+        (distance / self.scale).round() as i32
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum DragKind {
+    Move,
+    Resize
+}
+
+struct Drag {
+    kind: DragKind,
+    /// Kept for the whole drag, the canvas would move under the pointer otherwise
+    transform: Transform,
+    pointer_origin: (f64, f64),
+    /// The overlay under the pointer, the other selected ones follow it
+    grabbed: String,
+    /// The bounds of the dragged overlays when the drag started
+    origins: Vec<(String, Bounds)>,
+    has_moved: bool
+}
+
+#[derive(Default)]
+struct EditorState {
+    monitors: RefCell<Vec<EditorMonitor>>,
+    overlays: RefCell<Vec<EditorOverlay>>,
+    selection: RefCell<HashSet<String>>,
+    guides: RefCell<Vec<Guide>>,
+    drag: RefCell<Option<Drag>>
+}
+
+impl EditorState {
+    /// Fit the monitors and the overlays in the canvas
+    fn transform(&self, width: f64, height: f64) -> Transform {
+        let area = self.monitors
+            .borrow()
+            .iter()
+            .map(|monitor| monitor.bounds)
+            .chain(self.overlays.borrow().iter().map(|overlay| overlay.bounds))
+            .map(Bounds::to_rectangle)
+            .reduce(|area, rectangle| area.union(&rectangle))
+            .unwrap_or_else(|| gdk::Rectangle::new(0, 0, 1920, 1080));
+
+        let scale = ((width - 2.0 * CANVAS_MARGIN) / area.width().max(1) as f64)
+            .min((height - 2.0 * CANVAS_MARGIN) / area.height().max(1) as f64)
+            .max(0.01);
+        Transform {
+            scale,
+            offset_x: (width - area.width() as f64 * scale) / 2.0 - area.x() as f64 * scale,
+            offset_y: (height - area.height() as f64 * scale) / 2.0 - area.y() as f64 * scale
+        }
+    }
+
+    /// The topmost overlay at a point of the canvas
+    fn overlay_at(&self, transform: Transform, x: f64, y: f64) -> Option<String> {
+        self.overlays
+            .borrow()
+            .iter()
+            .rev()
+            .find(|overlay| {
+                let (left, top, width, height) = transform.to_canvas(overlay.bounds);
+                x >= left && x < left + width && y >= top && y < top + height
+            })
+            .map(|overlay| overlay.name.clone())
+    }
+
+    /// The selected overlay whose resize handle is at a point of the canvas
+    fn handle_at(&self, transform: Transform, x: f64, y: f64) -> Option<String> {
+        let selection = self.selection.borrow();
+        self.overlays
+            .borrow()
+            .iter()
+            .rev()
+            .filter(|overlay| selection.contains(&overlay.name))
+            .find(|overlay| {
+                let (left, top, width, height) = transform.to_canvas(overlay.bounds);
+                let (right, bottom) = (left + width, top + height);
+                x >= right - HANDLE_SIZE && x <= right && y >= bottom - HANDLE_SIZE && y <= bottom
+            })
+            .map(|overlay| overlay.name.clone())
+    }
+
+    fn selected_bounds(&self) -> Vec<(String, Bounds)> {
+        let selection = self.selection.borrow();
+        self.overlays
+            .borrow()
+            .iter()
+            .filter(|overlay| selection.contains(&overlay.name))
+            .map(|overlay| (overlay.name.clone(), overlay.bounds))
+            .collect()
+    }
+
+    fn set_bounds(&self, name: &str, bounds: Bounds) {
+        if let Some(overlay) = self.overlays.borrow_mut().iter_mut().find(|overlay| overlay.name == name) {
+            overlay.bounds = bounds;
+        }
+    }
+
+    fn readout(&self) -> String {
+        let selected_bounds = self.selected_bounds();
+        match selected_bounds.as_slice() {
+            [] => "Click an overlay to select it, Ctrl+click to select several, the arrow keys nudge the selection".to_string(),
+            [(name, bounds)] => format!("{}: x {}, y {}, {} × {} px", name, bounds.x, bounds.y, bounds.width, bounds.height),
+            _ => {
+                let area = selected_bounds
+                    .iter()
+                    .map(|(_, bounds)| bounds.to_rectangle())
+                    .reduce(|area, rectangle| area.union(&rectangle))
+                    .map(|area| Bounds::from_rectangle(&area))
+                    .unwrap_or_default();
+                format!("{} overlays selected: x {}, y {}, {} × {} px", selected_bounds.len(), area.x, area.y, area.width, area.height)
+            }
+        }
+    }
+}
+
+/// Draws the monitors to scale with the overlays on them, to move and resize the overlays with the mouse
+pub struct LayoutEditor {
+    window: gtk::Window,
+    canvas: gtk::DrawingArea,
+    readout: gtk::Label,
+    state: Rc<EditorState>
+}
+
+impl LayoutEditor {
+    pub fn new(event_sender: Sender<AppAction>) -> Self {
+        let window = gtk::Window::builder()
+            .title("Layout editor")
+            .default_width(900)
+            .default_height(600)
+            .build();
+        // Kept around to be shown again
+        window.connect_delete_event(|window, _| {
+            window.hide();
+            Inhibit(true)
+        });
+
+        let canvas = gtk::DrawingArea::builder()
+            .can_focus(true)
+            .hexpand(true)
+            .vexpand(true)
+            .build();
+        canvas.add_events(
+            gdk::EventMask::BUTTON_PRESS_MASK
+                | gdk::EventMask::BUTTON_RELEASE_MASK
+                | gdk::EventMask::POINTER_MOTION_MASK
+                | gdk::EventMask::KEY_PRESS_MASK
+        );
+
+        let readout = gtk::Label::builder()
+            .xalign(0.0)
+            .margin(8)
+            .selectable(true)
+            .build();
+
+        let container = gtk::Box::new(gtk::Orientation::Vertical, 0);
+        container.pack_start(&canvas, true, true, 0);
+        container.pack_end(&readout, false, false, 0);
+        window.add(&container);
+
+        let layout_editor = Self {
+            window,
+            canvas,
+            readout,
+            state: Rc::new(EditorState::default())
+        };
+        layout_editor.setup_signals(event_sender);
+        layout_editor.refresh();
+
+        layout_editor
+    }
+
+    pub fn show(&self) {
+        self.window.show_all();
+        self.window.present();
+        self.canvas.grab_focus();
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.window.is_visible()
+    }
+
+    /// Replace the displayed layout, the selection is kept. Ignored during a drag, the drag ends with an update.
+    pub fn set_layout(&self, monitors: Vec<EditorMonitor>, overlays: Vec<EditorOverlay>) {
+        if self.state.drag.borrow().is_some() {
+            return;
+        }
+
+        let names: HashSet<String> = overlays.iter().map(|overlay| overlay.name.clone()).collect();
+        self.state.selection.borrow_mut().retain(|name| names.contains(name));
+        *self.state.monitors.borrow_mut() = monitors;
+        *self.state.overlays.borrow_mut() = overlays;
+        self.refresh();
+    }
+
+    fn refresh(&self) {
+        LayoutEditor::refresh_widgets(&self.canvas, &self.readout, &self.state);
+    }
+
+    fn refresh_widgets(canvas: &gtk::DrawingArea, readout: &gtk::Label, state: &EditorState) {
+        readout.set_text(&state.readout());
+        canvas.queue_draw();
+    }
+
+    fn setup_signals(&self, event_sender: Sender<AppAction>) {
+        let state = self.state.clone();
+        self.canvas.connect_draw(move |canvas, ctx| {
+            LayoutEditor::draw(&state, canvas, ctx);
+            Inhibit(false)
+        });
+
+        let (state, readout) = (self.state.clone(), self.readout.clone());
+        self.canvas.connect_button_press_event(move |canvas, event| {
+            if event.button() != 1 || event.event_type() != gdk::EventType::ButtonPress {
+                return Inhibit(false);
+            }
+            canvas.grab_focus();
+
+            let (x, y) = event.position();
+            let transform = state.transform(canvas.allocated_width() as f64, canvas.allocated_height() as f64);
+            let is_adding = event.state().intersects(gdk::ModifierType::CONTROL_MASK | gdk::ModifierType::SHIFT_MASK);
+
+            let drag = if let Some(name) = state.handle_at(transform, x, y).filter(|_| !is_adding) {
+                let bounds = state.selected_bounds().into_iter().filter(|(other, _)| *other == name).collect();
+                Some((DragKind::Resize, name, bounds))
+            } else if let Some(name) = state.overlay_at(transform, x, y) {
+                if is_adding {
+                    let mut selection = state.selection.borrow_mut();
+                    if !selection.remove(&name) {
+                        selection.insert(name);
+                    }
+                    None
+                } else {
+                    if !state.selection.borrow().contains(&name) {
+                        *state.selection.borrow_mut() = HashSet::from([name.clone()]);
+                    }
+                    Some((DragKind::Move, name, state.selected_bounds()))
+                }
+            } else {
+                if !is_adding {
+                    state.selection.borrow_mut().clear();
+                }
+                None
+            };
+
+            *state.drag.borrow_mut() = drag.map(|(kind, grabbed, origins)| Drag {
+                kind,
+                transform,
+                pointer_origin: (x, y),
+                grabbed,
+                origins,
+                has_moved: false
+            });
+            LayoutEditor::refresh_widgets(canvas, &readout, &state);
+            Inhibit(true)
+        });
+
+        let (state, readout) = (self.state.clone(), self.readout.clone());
+        self.canvas.connect_motion_notify_event(move |canvas, event| {
+            let mut drag = state.drag.borrow_mut();
+            let Some(drag) = drag.as_mut() else {
+                return Inhibit(false);
+            };
+
+            let (x, y) = event.position();
+            let delta_x = drag.transform.to_screen_distance(x - drag.pointer_origin.0);
+            let delta_y = drag.transform.to_screen_distance(y - drag.pointer_origin.1);
+            drag.has_moved = drag.has_moved || delta_x != 0 || delta_y != 0;
+
+            match drag.kind {
+                DragKind::Resize => {
+                    for (name, origin) in drag.origins.iter() {
+                        state.set_bounds(name, Bounds {
+                            width: (origin.width + delta_x).max(MIN_SIZE),
+                            height: (origin.height + delta_y).max(MIN_SIZE),
+                            ..*origin
+                        });
+                    }
+                },
+                DragKind::Move => {
+                    // The grabbed overlay snaps, the other selected ones keep their distance to it
+                    let Some((_, grabbed_origin)) = drag.origins.iter().find(|(name, _)| *name == drag.grabbed) else {
+                        return Inhibit(false);
+                    };
+                    let moved_bounds = Bounds { x: grabbed_origin.x + delta_x, y: grabbed_origin.y + delta_y, ..*grabbed_origin };
+                    let targets = SnapTargets {
+                        monitors: state.monitors.borrow().iter().map(|monitor| monitor.bounds).collect(),
+                        overlays: state.overlays
+                            .borrow()
+                            .iter()
+                            .filter(|overlay| !drag.origins.iter().any(|(name, _)| *name == overlay.name))
+                            .map(|overlay| overlay.bounds)
+                            .collect(),
+                        grid: None
+                    };
+                    let (snapped_bounds, guides) = snap(moved_bounds, &targets);
+                    let (delta_x, delta_y) = (snapped_bounds.x - grabbed_origin.x, snapped_bounds.y - grabbed_origin.y);
+
+                    for (name, origin) in drag.origins.iter() {
+                        state.set_bounds(name, Bounds { x: origin.x + delta_x, y: origin.y + delta_y, ..*origin });
+                    }
+                    *state.guides.borrow_mut() = guides;
+                }
+            }
+
+            LayoutEditor::refresh_widgets(canvas, &readout, &state);
+            Inhibit(true)
+        });
+
+        let (state, event_sender_cloned) = (self.state.clone(), event_sender.clone());
+        self.canvas.connect_button_release_event(move |canvas, event| {
+            if event.button() != 1 {
+                return Inhibit(false);
+            }
+            let Some(drag) = state.drag.borrow_mut().take() else {
+                return Inhibit(false);
+            };
+
+            state.guides.borrow_mut().clear();
+            canvas.queue_draw();
+            if drag.has_moved {
+                let edits = state.selected_bounds()
+                    .into_iter()
+                    .filter(|(name, _)| drag.origins.iter().any(|(other, _)| other == name))
+                    .collect();
+                let _ = glib::MainContext::default().block_on(event_sender_cloned.send(AppAction::LayoutEdited(edits)));
+            }
+            Inhibit(true)
+        });
+
+        let (state, readout) = (self.state.clone(), self.readout.clone());
+        self.canvas.connect_key_press_event(move |canvas, event| {
+            if state.drag.borrow().is_some() {
+                return Inhibit(false);
+            }
+
+            let step = if event.state().contains(gdk::ModifierType::SHIFT_MASK) { LARGE_NUDGE_STEP } else { NUDGE_STEP };
+            let (delta_x, delta_y) = match event.keyval() {
+                gdk::keys::constants::Left => (-step, 0),
+                gdk::keys::constants::Right => (step, 0),
+                gdk::keys::constants::Up => (0, -step),
+                gdk::keys::constants::Down => (0, step),
+                gdk::keys::constants::a if event.state().contains(gdk::ModifierType::CONTROL_MASK) => {
+                    *state.selection.borrow_mut() = state.overlays.borrow().iter().map(|overlay| overlay.name.clone()).collect();
+                    LayoutEditor::refresh_widgets(canvas, &readout, &state);
+                    return Inhibit(true);
+                },
+                gdk::keys::constants::Escape => {
+                    state.selection.borrow_mut().clear();
+                    LayoutEditor::refresh_widgets(canvas, &readout, &state);
+                    return Inhibit(true);
+                },
+                _ => return Inhibit(false)
+            };
+
+            let edits: Vec<(String, Bounds)> = state.selected_bounds()
+                .into_iter()
+                .map(|(name, bounds)| (name, Bounds { x: bounds.x + delta_x, y: bounds.y + delta_y, ..bounds }))
+                .collect();
+            if edits.is_empty() {
+                return Inhibit(false);
+            }
+            for (name, bounds) in edits.iter() {
+                state.set_bounds(name, *bounds);
+            }
+            LayoutEditor::refresh_widgets(canvas, &readout, &state);
+
+            let _ = glib::MainContext::default().block_on(event_sender.send(AppAction::LayoutEdited(edits)));
+            Inhibit(true)
+        });
+    }
+
+    fn draw(state: &EditorState, canvas: &gtk::DrawingArea, ctx: &cairo::Context) {
+        let (width, height) = (canvas.allocated_width() as f64, canvas.allocated_height() as f64);
+        let transform = match state.drag.borrow().as_ref() {
+            Some(drag) => drag.transform,
+            None => state.transform(width, height)
+        };
+
+        ctx.set_source_rgb(0.15, 0.15, 0.15);
+        let _ = ctx.paint();
+        ctx.set_font_size(12.0);
+
+        for monitor in state.monitors.borrow().iter() {
+            let (x, y, monitor_width, monitor_height) = transform.to_canvas(monitor.bounds);
+            ctx.rectangle(x, y, monitor_width, monitor_height);
+            ctx.set_source_rgb(0.25, 0.25, 0.25);
+            let _ = ctx.fill_preserve();
+            ctx.set_source_rgb(0.6, 0.6, 0.6);
+            ctx.set_line_width(1.0);
+            let _ = ctx.stroke();
+
+            ctx.move_to(x + 6.0, y + monitor_height - 8.0);
+            let _ = ctx.show_text(&format!("{} ({} × {})", monitor.name, monitor.bounds.width, monitor.bounds.height));
+        }
+
+        let selection = state.selection.borrow();
+        for overlay in state.overlays.borrow().iter() {
+            let (x, y, overlay_width, overlay_height) = transform.to_canvas(overlay.bounds);
+            let is_selected = selection.contains(&overlay.name);
+
+            ctx.rectangle(x, y, overlay_width, overlay_height);
+            if overlay.is_active {
+                ctx.set_source_rgba(0.2, 0.5, 0.9, 0.5);
+            } else {
+                ctx.set_source_rgba(0.6, 0.6, 0.6, 0.3);
+            }
+            let _ = ctx.fill_preserve();
+            if is_selected {
+                ctx.set_source_rgb(1.0, 0.6, 0.1);
+                ctx.set_line_width(2.0);
+            } else {
+                ctx.set_source_rgb(0.8, 0.8, 0.8);
+                ctx.set_line_width(1.0);
+            }
+            let _ = ctx.stroke();
+
+            // The name stays inside its overlay
+            let _ = ctx.save();
+            ctx.rectangle(x, y, overlay_width, overlay_height);
+            ctx.clip();
+            ctx.set_source_rgb(1.0, 1.0, 1.0);
+            ctx.move_to(x + 4.0, y + 14.0);
+            let _ = ctx.show_text(&overlay.name);
+            let _ = ctx.restore();
+
+            if is_selected {
+                ctx.rectangle(x + overlay_width - HANDLE_SIZE, y + overlay_height - HANDLE_SIZE, HANDLE_SIZE, HANDLE_SIZE);
+                ctx.set_source_rgb(1.0, 0.6, 0.1);
+                let _ = ctx.fill();
+            }
+        }
+
+        ctx.set_source_rgb(1.0, 0.2, 0.8);
+        ctx.set_line_width(1.0);
+        ctx.set_dash(&[4.0, 4.0], 0.0);
+        for guide in state.guides.borrow().iter() {
+            match *guide {
+                Guide::Vertical(position) => {
+                    let x = transform.offset_x + position as f64 * transform.scale;
+                    ctx.move_to(x, 0.0);
+                    ctx.line_to(x, height);
+                },
+                Guide::Horizontal(position) => {
+                    let y = transform.offset_y + position as f64 * transform.scale;
+                    ctx.move_to(0.0, y);
+                    ctx.line_to(width, y);
+                }
+            }
+        }
+        let _ = ctx.stroke();
+        ctx.set_dash(&[], 0.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A monitor left of the primary one, the screen starts at a negative x
+    fn state(overlays: Vec<EditorOverlay>) -> EditorState {
+        let state = EditorState::default();
+        *state.monitors.borrow_mut() = vec![
+            EditorMonitor { name: "DP-2".to_string(), bounds: Bounds { x: -1920, y: 0, width: 1920, height: 1080 } },
+            EditorMonitor { name: "DP-1".to_string(), bounds: Bounds { x: 0, y: 0, width: 2560, height: 1440 } }
+        ];
+        *state.overlays.borrow_mut() = overlays;
+        state
+    }
+
+    fn overlay(name: &str, x: i32, y: i32) -> EditorOverlay {
+        EditorOverlay { name: name.to_string(), bounds: Bounds { x, y, width: 400, height: 300 }, is_active: true }
+    }
+
+    #[test]
+    fn the_monitors_are_scaled_into_the_canvas_and_back() {
+        let state = state(Vec::new());
+        // The screen is 4480 × 1440, the width limits the scale to a quarter
+        let transform = state.transform(1160.0, 500.0);
+        assert_eq!((transform.scale, transform.offset_x, transform.offset_y), (0.25, 500.0, 70.0));
+
+        let monitors = state.monitors.borrow();
+        assert_eq!(transform.to_canvas(monitors[0].bounds), (20.0, 70.0, 480.0, 270.0));
+        assert_eq!(transform.to_canvas(monitors[1].bounds), (500.0, 70.0, 640.0, 360.0));
+
+        for screen_x in [-1920, -1000, 0, 1234, 2560] {
+            let (canvas_x, _, _, _) = transform.to_canvas(Bounds { x: screen_x, y: 0, width: 1, height: 1 });
+            assert_eq!(transform.to_screen_distance(canvas_x - transform.offset_x), screen_x);
+        }
+        assert_eq!(transform.to_screen_distance(480.0), 1920);
+        assert_eq!(transform.to_screen_distance(0.6), 2);
+    }
+
+    #[test]
+    fn the_topmost_overlay_is_hit() {
+        let state = state(vec![overlay("Meter", 0, 0), overlay("Timers", 200, 100)]);
+        let transform = state.transform(1160.0, 500.0);
+
+        // Screen (300, 200) is in both, (100, 50) only in the first one
+        assert_eq!(state.overlay_at(transform, 575.0, 120.0).as_deref(), Some("Timers"));
+        assert_eq!(state.overlay_at(transform, 525.0, 82.5).as_deref(), Some("Meter"));
+        // On the left monitor, away from the overlays
+        assert_eq!(state.overlay_at(transform, 375.0, 300.0), None);
+        // The right and bottom edges are outside
+        assert_eq!(state.overlay_at(transform, 650.0, 120.0), None);
+    }
+
+    #[test]
+    fn the_resize_handle_is_in_the_corner_of_the_selected_overlays() {
+        let state = state(vec![overlay("Meter", 0, 0), overlay("Timers", 200, 100)]);
+        let transform = state.transform(1160.0, 500.0);
+        state.selection.borrow_mut().insert("Meter".to_string());
+
+        // The corner of Meter is at (600, 145) on the canvas, under Timers
+        assert_eq!(state.handle_at(transform, 595.0, 140.0).as_deref(), Some("Meter"));
+        assert_eq!(state.handle_at(transform, 600.0, 145.0).as_deref(), Some("Meter"));
+        assert_eq!(state.handle_at(transform, 585.0, 140.0), None);
+        // Timers is not selected, its corner is not a handle
+        assert_eq!(state.handle_at(transform, 645.0, 165.0), None);
+    }
+}
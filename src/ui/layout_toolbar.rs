@@ -0,0 +1,67 @@
+use async_channel::Sender;
+use gtk::prelude::*;
+
+use crate::app::AppAction;
+use crate::overlay::edit_mode::EditSettings;
+
+const DEFAULT_GRID_SIZE: f64 = 20.0;
+
+pub struct LayoutToolbar {
+    pub container: gtk::Box,
+    edit_button: gtk::ToggleButton,
+    layout_editor_button: gtk::Button,
+    grid_check: gtk::CheckButton,
+    grid_spin: gtk::SpinButton
+}
+
+impl LayoutToolbar {
+    pub fn new(event_sender: Sender<AppAction>) -> Self {
+        let container = gtk::Box::new(gtk::Orientation::Horizontal, 10);
+        container.style_context().add_class("layout-toolbar");
+
+        let grid_spin = gtk::SpinButton::with_range(2.0, 200.0, 1.0);
+        grid_spin.set_value(DEFAULT_GRID_SIZE);
+
+        let layout_toolbar = Self {
+            container,
+            edit_button: gtk::ToggleButton::with_label("Edit layout"),
+            layout_editor_button: gtk::Button::with_label("Layout editor"),
+            grid_check: gtk::CheckButton::with_label("Snap to grid"),
+            grid_spin
+        };
+
+        layout_toolbar.container.add(&layout_toolbar.edit_button);
+        layout_toolbar.container.add(&layout_toolbar.grid_check);
+        layout_toolbar.container.add(&layout_toolbar.grid_spin);
+        layout_toolbar.container.pack_end(&layout_toolbar.layout_editor_button, false, false, 0);
+
+        layout_toolbar.setup_signals(event_sender);
+
+        layout_toolbar
+    }
+
+    fn setup_signals(&self, event_sender: Sender<AppAction>) {
+        let event_sender_clone = event_sender.clone();
+        self.layout_editor_button.connect_clicked(move |_| {
+            let _ = glib::MainContext::default().block_on(event_sender_clone.send(AppAction::OpenLayoutEditor));
+        });
+
+        let edit_button = self.edit_button.clone();
+        let grid_check = self.grid_check.clone();
+        let grid_spin = self.grid_spin.clone();
+        let send_settings = move || {
+            let settings = edit_button.is_active().then(|| EditSettings {
+                grid: grid_check.is_active().then(|| grid_spin.value_as_int())
+            });
+            let _ = glib::MainContext::default().block_on(event_sender.send(AppAction::SetEditMode(settings)));
+        };
+
+        let send_settings_clone = send_settings.clone();
+        self.edit_button.connect_toggled(move |_| send_settings_clone());
+
+        let send_settings_clone = send_settings.clone();
+        self.grid_check.connect_toggled(move |_| send_settings_clone());
+
+        self.grid_spin.connect_value_changed(move |_| send_settings());
+    }
+}
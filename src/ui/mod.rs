@@ -1,33 +1,47 @@
 use async_channel::Sender;
-use gtk::traits::{PanedExt, WidgetExt, ContainerExt};
+use gtk::traits::{PanedExt, WidgetExt, ContainerExt, BoxExt};
 
 use crate::app::AppAction;
 
-use self::{sidebar::Sidebar, overlay_infos::OverlayDetails};
+use self::{sidebar::Sidebar, overlay_infos::OverlayDetails, replay_bar::ReplayBar, layout_toolbar::LayoutToolbar};
 
+pub mod catalog_dialog;
+pub mod layout_editor;
+pub mod layout_toolbar;
 pub mod overlay_infos;
+pub mod overlay_preview;
+pub mod replay_bar;
 pub mod sidebar;
 
 pub struct AppContainer {
-    pub container: gtk::Paned,
+    pub container: gtk::Box,
     pub sidebar: Sidebar,
-    pub overlay_details: OverlayDetails
+    pub overlay_details: OverlayDetails,
+    pub replay_bar: ReplayBar
 }
 
 impl AppContainer {
     pub fn new(event_sender: Sender<AppAction>) -> Self{
-        let container = gtk::Paned::new(gtk::Orientation::Horizontal);
+        let container = gtk::Box::new(gtk::Orientation::Vertical, 0);
+        let paned = gtk::Paned::new(gtk::Orientation::Horizontal);
 
         let sidebar = Sidebar::new(event_sender.clone());
-        let overlay_details = OverlayDetails::new(event_sender);
+        let overlay_details = OverlayDetails::new(event_sender.clone());
+        let replay_bar = ReplayBar::new(event_sender.clone());
+        let layout_toolbar = LayoutToolbar::new(event_sender);
         
-        container.pack1(&sidebar.frame, false, false);
-        container.pack2(&overlay_details.container, true, true);
+        paned.pack1(&sidebar.frame, false, false);
+        paned.pack2(&overlay_details.container, true, true);
+
+        container.pack_start(&layout_toolbar.container, false, false, 0);
+        container.pack_start(&paned, true, true, 0);
+        container.pack_end(&replay_bar.container, false, false, 0);
 
         Self {
             container,
             sidebar,
-            overlay_details
+            overlay_details,
+            replay_bar
         }
     }
 
@@ -1,8 +1,15 @@
+use std::time::Duration;
+
 use async_channel::Sender;
 use glib::SignalHandlerId;
 use gtk::prelude::*;
 
-use crate::{layout_config::LayoutConfig, app::AppAction};
+use crate::{layout_config::{Anchor, ClickthroughMode, CombatCondition, ConfigField, FieldError, IdleBehaviour, LayoutConfig, SizeUnit, Visibility, VisibilityConditions, MAX_ZOOM, MIN_ZOOM}, app::AppAction, overlay::placement};
+
+use super::overlay_preview::OverlayPreview;
+
+/// Id of the monitor combo entry for absolute coordinates
+const WHOLE_SCREEN_ID: &str = "";
 
 pub struct OverlayDetails {
     event_sender: Sender<AppAction>,
@@ -13,14 +20,42 @@ pub struct OverlayDetails {
     
     pub name_entry: gtk::Entry,
     pub url_entry: gtk::Entry,
+    pub tags_entry: gtk::Entry,
+    pub monitor_combo: gtk::ComboBoxText,
+    pub follow_game_check: gtk::CheckButton,
+    pub anchor_combo: gtk::ComboBoxText,
+    pub size_unit_combo: gtk::ComboBoxText,
     pub x_pos_spin: gtk::SpinButton,
     pub y_pos_spin: gtk::SpinButton,
     pub width_spin: gtk::SpinButton,
     pub height_spin: gtk::SpinButton,
+    pub zoom_spin: gtk::SpinButton,
+    pub user_css_view: gtk::TextView,
     pub clickthrough_check: gtk::CheckButton,
+    pub clickthrough_mode_combo: gtk::ComboBoxText,
+    pub sampling_interval_spin: gtk::SpinButton,
     pub movable_check: gtk::CheckButton,
+    pub visibility_combo: gtk::ComboBoxText,
+    pub combat_combo: gtk::ComboBoxText,
+    pub zones_entry: gtk::Entry,
+    pub jobs_entry: gtk::Entry,
+    pub idle_behaviour_combo: gtk::ComboBoxText,
+    pub idle_delay_spin: gtk::SpinButton,
+    pub idle_opacity_spin: gtk::SpinButton,
+
+    /// Shown below the fields while their value is invalid
+    name_error: gtk::Label,
+    url_error: gtk::Label,
+    width_error: gtk::Label,
+    height_error: gtk::Label,
 
     save_button: gtk::Button,
+    revert_button: gtk::Button,
+    preview: OverlayPreview,
+
+    /// The overlay as it was loaded, and the form values it was displayed with
+    current_overlay: LayoutConfig,
+    form_baseline: LayoutConfig,
 
     switch_handler_id: Option<SignalHandlerId>,
     save_handler_id: Option<SignalHandlerId>
@@ -42,13 +77,38 @@ impl OverlayDetails {
 
             name_entry: gtk::Entry::default(),
             url_entry: gtk::Entry::default(),
-            x_pos_spin: OverlayDetails::create_spinbutton(),
-            y_pos_spin: OverlayDetails::create_spinbutton(),
-            width_spin: OverlayDetails::create_spinbutton(),
-            height_spin: OverlayDetails::create_spinbutton(),
+            tags_entry: gtk::Entry::builder().placeholder_text("Keywords separated by commas").build(),
+            monitor_combo: gtk::ComboBoxText::new(),
+            follow_game_check: gtk::CheckButton::with_label("Follow the game window"),
+            anchor_combo: OverlayDetails::create_anchor_combo(),
+            size_unit_combo: OverlayDetails::create_size_unit_combo(),
+            x_pos_spin: OverlayDetails::create_spinbutton(-5000.0),
+            y_pos_spin: OverlayDetails::create_spinbutton(-5000.0),
+            width_spin: OverlayDetails::create_spinbutton(0.0),
+            height_spin: OverlayDetails::create_spinbutton(0.0),
+            zoom_spin: gtk::SpinButton::with_range(MIN_ZOOM as f64, MAX_ZOOM as f64, 5.0),
+            user_css_view: gtk::TextView::builder().monospace(true).wrap_mode(gtk::WrapMode::WordChar).build(),
             clickthrough_check: gtk::CheckButton::with_label("Clickthrough"),
+            clickthrough_mode_combo: OverlayDetails::create_clickthrough_mode_combo(),
+            sampling_interval_spin: gtk::SpinButton::with_range(100.0, 10000.0, 100.0),
             movable_check: gtk::CheckButton::with_label("Movable"),
+            visibility_combo: OverlayDetails::create_visibility_combo(),
+            combat_combo: OverlayDetails::create_combat_combo(),
+            zones_entry: gtk::Entry::builder().placeholder_text("Zone ids, any zone if empty").build(),
+            jobs_entry: gtk::Entry::builder().placeholder_text("Jobs like WHM, SCH, any job if empty").build(),
+            idle_behaviour_combo: OverlayDetails::create_idle_behaviour_combo(),
+            idle_delay_spin: gtk::SpinButton::with_range(1.0, 3600.0, 1.0),
+            idle_opacity_spin: gtk::SpinButton::with_range(0.0, 100.0, 5.0),
+            name_error: OverlayDetails::create_form_error(),
+            url_error: OverlayDetails::create_form_error(),
+            width_error: OverlayDetails::create_form_error(),
+            height_error: OverlayDetails::create_form_error(),
             save_button: gtk::Button::builder().label("Save").build(),
+            revert_button: gtk::Button::builder().label("Revert").sensitive(false).build(),
+            preview: OverlayPreview::new(),
+
+            current_overlay: LayoutConfig::default(),
+            form_baseline: LayoutConfig::default(),
 
             switch_handler_id: None,
             save_handler_id: None
@@ -57,17 +117,127 @@ impl OverlayDetails {
         let header = overlay_details.create_header();
         let form = overlay_details.create_form();
     
+        // The preview stays next to the form
+        let body = gtk::Box::new(gtk::Orientation::Horizontal, 0);
+        body.pack_start(&form, true, true, 0);
+        body.pack_start(&overlay_details.preview.container, false, false, 0);
+
         overlay_details.container.add(&header);
-        overlay_details.container.add(&form);
+        overlay_details.container.add(&body);
+        overlay_details.connect_form_changes();
 
         overlay_details
     }
+
+    /// Any edit of the form is reported to refresh the modified state
+    fn connect_form_changes(&self) {
+        let notify_change = {
+            let event_sender = self.event_sender.clone();
+            move || {
+                let event_sender = event_sender.clone();
+                glib::MainContext::default().spawn_local(async move {
+                    let _ = event_sender.send(AppAction::OverlayFormChanged).await;
+                });
+            }
+        };
+
+        for entry in [&self.name_entry, &self.url_entry, &self.tags_entry, &self.zones_entry, &self.jobs_entry] {
+            let notify_change = notify_change.clone();
+            entry.connect_changed(move |_| notify_change());
+        }
+        for combo in [
+            &self.monitor_combo,
+            &self.anchor_combo,
+            &self.size_unit_combo,
+            &self.clickthrough_mode_combo,
+            &self.visibility_combo,
+            &self.combat_combo,
+            &self.idle_behaviour_combo
+        ] {
+            let notify_change = notify_change.clone();
+            combo.connect_changed(move |_| notify_change());
+        }
+        for check in [&self.follow_game_check, &self.clickthrough_check, &self.movable_check] {
+            let notify_change = notify_change.clone();
+            check.connect_toggled(move |_| notify_change());
+        }
+        for spin in [
+            &self.x_pos_spin,
+            &self.y_pos_spin,
+            &self.width_spin,
+            &self.height_spin,
+            &self.zoom_spin,
+            &self.sampling_interval_spin,
+            &self.idle_delay_spin,
+            &self.idle_opacity_spin
+        ] {
+            let notify_change = notify_change.clone();
+            spin.connect_value_changed(move |_| notify_change());
+        }
+        if let Some(buffer) = self.user_css_view.buffer() {
+            let notify_change = notify_change.clone();
+            buffer.connect_changed(move |_| notify_change());
+        }
+
+        let event_sender = self.event_sender.clone();
+        self.revert_button.connect_clicked(move |_| {
+            let _ = glib::MainContext::default().block_on(event_sender.send(AppAction::RevertOverlay));
+        });
+    }
     
-    fn create_spinbutton() -> gtk::SpinButton {
-        let adjustment = gtk::Adjustment::new(0.0, 0.0, 5000.0, 1.0, 1.0, 0.0);
+    /// The offsets go below zero to reach the monitors left of or above the origin
+    fn create_spinbutton(lower: f64) -> gtk::SpinButton {
+        let adjustment = gtk::Adjustment::new(0.0, lower, 5000.0, 1.0, 1.0, 0.0);
         gtk::SpinButton::new(Some(&adjustment), 1.0, 0)
     }
     
+    fn create_anchor_combo() -> gtk::ComboBoxText {
+        let combo = gtk::ComboBoxText::new();
+        for anchor in Anchor::ALL.iter() {
+            combo.append_text(anchor.label());
+        }
+        combo
+    }
+
+    fn create_clickthrough_mode_combo() -> gtk::ComboBoxText {
+        let combo = gtk::ComboBoxText::new();
+        for mode in ClickthroughMode::ALL.iter() {
+            combo.append_text(mode.label());
+        }
+        combo
+    }
+
+    fn create_visibility_combo() -> gtk::ComboBoxText {
+        let combo = gtk::ComboBoxText::new();
+        for visibility in Visibility::ALL.iter() {
+            combo.append_text(visibility.label());
+        }
+        combo
+    }
+
+    fn create_combat_combo() -> gtk::ComboBoxText {
+        let combo = gtk::ComboBoxText::new();
+        for combat in CombatCondition::ALL.iter() {
+            combo.append_text(combat.label());
+        }
+        combo
+    }
+
+    fn create_idle_behaviour_combo() -> gtk::ComboBoxText {
+        let combo = gtk::ComboBoxText::new();
+        for behaviour in IdleBehaviour::ALL.iter() {
+            combo.append_text(behaviour.label());
+        }
+        combo
+    }
+
+    fn create_size_unit_combo() -> gtk::ComboBoxText {
+        let combo = gtk::ComboBoxText::new();
+        combo.append_text("Pixels");
+        combo.append_text("Percent of the monitor");
+        combo
+    }
+
     fn create_header(&mut self) -> gtk::Widget {
         let header = gtk::Box::builder()
             .orientation(gtk::Orientation::Horizontal)
@@ -91,28 +261,68 @@ impl OverlayDetails {
         form_box.set_margin_top(30);
 
         form_box.add(&self.create_form_entry("Name", &self.name_entry));
+        form_box.add(&self.name_error);
         form_box.add(&self.create_form_entry("Overlay url", &self.url_entry));
-        form_box.add(&self.create_form_spinbox("X position", &self.x_pos_spin));
-        form_box.add(&self.create_form_spinbox("Y position", &self.y_pos_spin));
+        form_box.add(&self.url_error);
+        form_box.add(&self.create_form_entry("Tags", &self.tags_entry));
+        form_box.add(&self.create_form_combo("Monitor", &self.monitor_combo));
+
+        // The monitor is used while the game window is not found
+        self.follow_game_check.set_margin_start(50);
+        form_box.add(&self.follow_game_check);
+
+        form_box.add(&self.create_form_combo("Anchor", &self.anchor_combo));
+        form_box.add(&self.create_form_spinbox("X offset", &self.x_pos_spin));
+        form_box.add(&self.create_form_spinbox("Y offset", &self.y_pos_spin));
+        form_box.add(&self.create_form_combo("Size unit", &self.size_unit_combo));
         form_box.add(&self.create_form_spinbox("Width", &self.width_spin));
+        form_box.add(&self.width_error);
         form_box.add(&self.create_form_spinbox("Height", &self.height_spin));
+        form_box.add(&self.height_error);
+        form_box.add(&self.create_form_spinbox("Zoom (%)", &self.zoom_spin));
+        form_box.add(&self.create_form_text_view("User CSS", &self.user_css_view));
 
         self.clickthrough_check.set_margin_start(50);
         form_box.add(&self.clickthrough_check);
+        form_box.add(&self.create_form_combo("Clickable", &self.clickthrough_mode_combo));
+        form_box.add(&self.create_form_spinbox("Sampling (ms)", &self.sampling_interval_spin));
 
         self.movable_check.set_margin_start(50);
         form_box.add(&self.movable_check);
 
-        form_box.add(&self.save_button);
+        form_box.add(&self.create_form_combo("Visible", &self.visibility_combo));
+        form_box.add(&self.create_form_combo("Combat", &self.combat_combo));
+        form_box.add(&self.create_form_entry("Zones", &self.zones_entry));
+        form_box.add(&self.create_form_entry("Jobs", &self.jobs_entry));
+
+        form_box.add(&self.create_form_combo("When idle", &self.idle_behaviour_combo));
+        form_box.add(&self.create_form_spinbox("Idle after (s)", &self.idle_delay_spin));
+        form_box.add(&self.create_form_spinbox("Idle opacity (%)", &self.idle_opacity_spin));
+
+        let buttons_box = gtk::Box::new(gtk::Orientation::Horizontal, 10);
+        buttons_box.pack_start(&self.save_button, true, true, 0);
+        buttons_box.pack_start(&self.revert_button, false, false, 0);
+        form_box.add(&buttons_box);
 
         form_box.into()
     }
 
+    /// Aligned with the inputs, hidden until there is an error to show
+    fn create_form_error() -> gtk::Label {
+        let label = gtk::Label::builder()
+            .xalign(0.0)
+            .margin_start(100)
+            .no_show_all(true)
+            .build();
+        label.style_context().add_class("form-error");
+        label
+    }
+
     fn create_form_entry(&self, name: &str, widget: &gtk::Entry) -> gtk::Box {
         let form_box = gtk::Box::new(gtk::Orientation::Horizontal, 0);
         form_box.set_hexpand(true);
 
-        let label = gtk::Label::new(Some(&name));
+        let label = gtk::Label::new(Some(name));
         label.set_size_request(100, 30);
         form_box.add(&label);
         widget.set_hexpand(true);
@@ -125,7 +335,39 @@ impl OverlayDetails {
         let form_box = gtk::Box::new(gtk::Orientation::Horizontal, 0);
         form_box.set_hexpand(true);
 
-        let label = gtk::Label::new(Some(&name));
+        let label = gtk::Label::new(Some(name));
+        label.set_size_request(100, 30);
+        form_box.add(&label);
+        widget.set_hexpand(true);
+        form_box.add(widget);
+
+        form_box
+    }
+
+    /// The text grows with its lines, the label stays at the top
+    fn create_form_text_view(&self, name: &str, widget: &gtk::TextView) -> gtk::Box {
+        let form_box = gtk::Box::new(gtk::Orientation::Horizontal, 0);
+        form_box.set_hexpand(true);
+
+        let label = gtk::Label::new(Some(name));
+        label.set_size_request(100, 30);
+        label.set_valign(gtk::Align::Start);
+        form_box.add(&label);
+
+        let frame = gtk::Frame::new(None);
+        widget.set_size_request(-1, 60);
+        frame.add(widget);
+        frame.set_hexpand(true);
+        form_box.add(&frame);
+
+        form_box
+    }
+
+    fn create_form_combo(&self, name: &str, widget: &gtk::ComboBoxText) -> gtk::Box {
+        let form_box = gtk::Box::new(gtk::Orientation::Horizontal, 0);
+        form_box.set_hexpand(true);
+
+        let label = gtk::Label::new(Some(name));
         label.set_size_request(100, 30);
         form_box.add(&label);
         widget.set_hexpand(true);
@@ -134,25 +376,282 @@ impl OverlayDetails {
         form_box
     }
 
+    /// List the connected monitors, keeping the one of the overlay even if it is unplugged
+    fn fill_monitor_combo(&self, selected_monitor: Option<String>) {
+        self.monitor_combo.remove_all();
+        self.monitor_combo.append(Some(WHOLE_SCREEN_ID), "Whole screen (absolute position)");
+
+        let display = WidgetExt::display(&self.monitor_combo);
+        let monitors = placement::monitors(&display);
+        for monitor in monitors.iter() {
+            let label = if monitor.is_primary { format!("{} (primary)", monitor.identifier) } else { monitor.identifier.clone() };
+            self.monitor_combo.append(Some(&monitor.identifier), &label);
+        }
+
+        if let Some(selected_monitor) = &selected_monitor {
+            if !monitors.iter().any(|monitor| &monitor.identifier == selected_monitor) {
+                self.monitor_combo.append(Some(selected_monitor), &format!("{} (disconnected)", selected_monitor));
+            }
+        }
+
+        self.monitor_combo.set_active_id(Some(selected_monitor.as_deref().unwrap_or(WHOLE_SCREEN_ID)));
+    }
+
+    pub fn selected_monitor(&self) -> Option<String> {
+        self.monitor_combo
+            .active_id()
+            .map(|id| id.to_string())
+            .filter(|id| id != WHOLE_SCREEN_ID)
+    }
+
+    pub fn selected_clickthrough_mode(&self) -> ClickthroughMode {
+        self.clickthrough_mode_combo
+            .active()
+            .and_then(|index| ClickthroughMode::ALL.get(index as usize))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    pub fn selected_visibility(&self) -> Visibility {
+        self.visibility_combo
+            .active()
+            .and_then(|index| Visibility::ALL.get(index as usize))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    pub fn selected_idle_behaviour(&self) -> IdleBehaviour {
+        self.idle_behaviour_combo
+            .active()
+            .and_then(|index| IdleBehaviour::ALL.get(index as usize))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    pub fn tags(&self) -> Vec<String> {
+        self.tags_entry
+            .text()
+            .split(',')
+            .map(str::trim)
+            .filter(|tag| !tag.is_empty())
+            .map(str::to_string)
+            .collect()
+    }
+
+    pub fn user_css(&self) -> String {
+        self.user_css_view
+            .buffer()
+            .and_then(|buffer| buffer.text(&buffer.start_iter(), &buffer.end_iter(), false))
+            .map(|text| text.to_string())
+            .unwrap_or_default()
+    }
+
+    /// The zones and jobs are separated by commas, invalid zone ids are ignored
+    pub fn visibility_conditions(&self) -> VisibilityConditions {
+        let combat = self.combat_combo
+            .active()
+            .and_then(|index| CombatCondition::ALL.get(index as usize))
+            .copied()
+            .unwrap_or_default();
+        let zones_text = self.zones_entry.text();
+        let jobs_text = self.jobs_entry.text();
+        let split_list = |text: &str| text
+            .split(',')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+
+        VisibilityConditions {
+            combat,
+            zones: split_list(&zones_text).iter().filter_map(|zone| zone.parse().ok()).collect(),
+            jobs: split_list(&jobs_text).iter().map(|job| job.to_uppercase()).collect()
+        }
+    }
+
+    pub fn selected_anchor(&self) -> Anchor {
+        self.anchor_combo
+            .active()
+            .and_then(|index| Anchor::ALL.get(index as usize))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    pub fn selected_size_unit(&self) -> SizeUnit {
+        match self.size_unit_combo.active() {
+            Some(1) => SizeUnit::Percent,
+            _ => SizeUnit::Pixels
+        }
+    }
+
+    /// The name of the saved overlay being displayed, empty for a new one
+    pub fn current_overlay_name(&self) -> String {
+        self.current_overlay.name()
+    }
+
+    /// The overlay as it was loaded in the form, without the edits
+    pub fn current_overlay(&self) -> LayoutConfig {
+        self.current_overlay.clone()
+    }
+
+    /// Write the values of the form in the overlay
+    fn apply_form(&self, overlay: &mut LayoutConfig) {
+        overlay.set_name(self.name_entry.text());
+        overlay.set_url(self.url_entry.text());
+        overlay.set_tags(self.tags());
+        overlay.set_monitor(self.selected_monitor());
+        overlay.set_follow_game_window(self.follow_game_check.is_active());
+        overlay.set_anchor(self.selected_anchor());
+        overlay.set_size_unit(self.selected_size_unit());
+        overlay.set_x(self.x_pos_spin.value_as_int());
+        overlay.set_y(self.y_pos_spin.value_as_int());
+        overlay.set_width(self.width_spin.value_as_int());
+        overlay.set_height(self.height_spin.value_as_int());
+        overlay.set_zoom(self.zoom_spin.value() / 100.0);
+        overlay.set_user_css(self.user_css());
+        overlay.set_is_clickthrough(self.clickthrough_check.is_active());
+        overlay.set_clickthrough_mode(self.selected_clickthrough_mode());
+        overlay.set_alpha_sampling_interval(Duration::from_millis(self.sampling_interval_spin.value_as_int() as u64));
+        overlay.set_is_decorated(self.movable_check.is_active());
+        overlay.set_visibility(self.selected_visibility());
+        overlay.set_conditions(self.visibility_conditions());
+        overlay.set_idle_behaviour(self.selected_idle_behaviour());
+        overlay.set_idle_delay(Duration::from_secs(self.idle_delay_spin.value_as_int() as u64));
+        overlay.set_idle_opacity(self.idle_opacity_spin.value() / 100.0);
+    }
+
+    /// The form values alone, the fields which are not in the form keep their default
+    pub fn form_values(&self) -> LayoutConfig {
+        let mut overlay = LayoutConfig::default();
+        self.apply_form(&mut overlay);
+        overlay
+    }
+
+    /// Compared to the values read right after loading, so that the form rounding does not count as an edit
+    pub fn is_modified(&self) -> bool {
+        self.form_values() != self.form_baseline
+    }
+
+    /// The errors of the form values, the name must stay free for a new or renamed overlay
+    pub fn form_errors(&self) -> Vec<FieldError> {
+        self.form_values().validate_rename(&self.current_overlay.name())
+    }
+
+    /// Update the title mark, the revert button and the errors of the fields
+    pub fn refresh_form_state(&self) {
+        let is_modified = self.is_modified();
+        let title = if is_modified { format!("{} *", self.current_overlay.name()) } else { self.current_overlay.name() };
+        self.title.set_text(&title);
+        self.revert_button.set_sensitive(is_modified);
+
+        let errors = self.form_errors();
+        for (field, error_label) in [
+            (ConfigField::Name, &self.name_error),
+            (ConfigField::Url, &self.url_error),
+            (ConfigField::Width, &self.width_error),
+            (ConfigField::Height, &self.height_error)
+        ] {
+            // Only the first error of a field is shown
+            match errors.iter().find(|error| error.field == field) {
+                Some(error) => {
+                    error_label.set_text(&error.message);
+                    error_label.show();
+                },
+                None => error_label.hide()
+            }
+        }
+        self.save_button.set_sensitive(errors.is_empty());
+    }
+
+    /// Render the form values in the preview, `overlay_url` gives the url the overlay is actually opened with
+    pub fn refresh_preview(&self, overlay_url: impl Fn(&str) -> String) {
+        let overlay = self.form_values();
+        let is_url_valid = !overlay.validate().iter().any(|error| error.field == ConfigField::Url);
+        let url = if is_url_valid { overlay_url(&overlay.url()) } else { String::new() };
+
+        let display = WidgetExt::display(&self.container);
+        let geometry = placement::overlay_geometry(&overlay, &display, None);
+        self.preview.update(url, geometry.width(), geometry.height(), overlay.zoom(), &overlay.user_css());
+    }
+
+    /// Take the new saved state of the displayed overlay, the fields which were not edited show the saved values
+    pub fn set_saved_overlay(&mut self, overlay: LayoutConfig) {
+        self.disconnect_signals();
+        self.active_state_switch.set_state(overlay.is_active());
+
+        let form = self.form_values();
+        self.fill_form(&form.merge_unedited(&self.form_baseline, &overlay));
+        // Read back, the form may round the saved values
+        self.form_baseline = self.form_baseline.merge_unedited(&form, &self.form_values());
+
+        self.current_overlay = overlay.clone();
+        self.setup_signals(overlay);
+        self.refresh_form_state();
+    }
+
     pub fn set_current_overlay(&mut self, overlay: LayoutConfig) {
         self.disconnect_signals();
 
         // Update the form entries
         self.title.set_text(&overlay.name());
         self.active_state_switch.set_state(overlay.is_active());
+        self.fill_form(&overlay);
+
+        self.current_overlay = overlay.clone();
+        self.form_baseline = self.form_values();
+        self.refresh_form_state();
+
+        self.setup_signals(overlay);
+    }
+
+    /// Display a new overlay pre-filled from a template, the template values count as unsaved changes
+    pub fn set_new_overlay(&mut self, template: LayoutConfig) {
+        self.set_current_overlay(LayoutConfig::default());
+        self.fill_form(&template);
+        self.refresh_form_state();
+    }
 
+    fn fill_form(&self, overlay: &LayoutConfig) {
         self.name_entry.set_text(&overlay.name());
         self.url_entry.set_text(&overlay.url());
+        self.tags_entry.set_text(&overlay.tags().join(", "));
+        self.fill_monitor_combo(overlay.monitor());
+        self.follow_game_check.set_active(overlay.follows_game_window());
+        let anchor_index = Anchor::ALL.iter().position(|anchor| *anchor == overlay.anchor()).unwrap_or(0);
+        self.anchor_combo.set_active(Some(anchor_index as u32));
+        self.size_unit_combo.set_active(Some(match overlay.size_unit() {
+            SizeUnit::Pixels => 0,
+            SizeUnit::Percent => 1
+        }));
         self.x_pos_spin.set_value(overlay.x() as f64);
         self.y_pos_spin.set_value(overlay.y() as f64);
         self.width_spin.set_value(overlay.width() as f64);
         self.height_spin.set_value(overlay.height() as f64);
+        self.zoom_spin.set_value((overlay.zoom() * 100.0).round());
+        if let Some(buffer) = self.user_css_view.buffer() {
+            buffer.set_text(&overlay.user_css());
+        }
         self.clickthrough_check.set_active(overlay.is_clickthrough());
+        let mode_index = ClickthroughMode::ALL.iter().position(|mode| *mode == overlay.clickthrough_mode()).unwrap_or(0);
+        self.clickthrough_mode_combo.set_active(Some(mode_index as u32));
+        self.sampling_interval_spin.set_value(overlay.alpha_sampling_interval().as_millis() as f64);
         self.movable_check.set_active(overlay.is_decoraded());
-        
-        self.setup_signals(overlay);
+        let visibility_index = Visibility::ALL.iter().position(|visibility| *visibility == overlay.visibility()).unwrap_or(0);
+        self.visibility_combo.set_active(Some(visibility_index as u32));
+        self.set_visibility_conditions(overlay.conditions());
+        let idle_behaviour_index = IdleBehaviour::ALL.iter().position(|behaviour| *behaviour == overlay.idle_behaviour()).unwrap_or(0);
+        self.idle_behaviour_combo.set_active(Some(idle_behaviour_index as u32));
+        self.idle_delay_spin.set_value(overlay.idle_delay().as_secs() as f64);
+        self.idle_opacity_spin.set_value((overlay.idle_opacity() * 100.0).round());
     }
     
+    pub fn set_visibility_conditions(&self, conditions: &VisibilityConditions) {
+        let combat_index = CombatCondition::ALL.iter().position(|combat| *combat == conditions.combat).unwrap_or(0);
+        self.combat_combo.set_active(Some(combat_index as u32));
+        self.zones_entry.set_text(&conditions.zones.iter().map(u32::to_string).collect::<Vec<_>>().join(", "));
+        self.jobs_entry.set_text(&conditions.jobs.join(", "));
+    }
+
     fn setup_signals(&mut self, overlay: LayoutConfig) {
         let overlay_cloned = overlay.clone();
         let event_sender = self.event_sender.clone();
@@ -161,7 +660,7 @@ impl OverlayDetails {
                 let event_sender = event_sender.clone();
                 let overlay = overlay_cloned.clone();
                 glib::MainContext::default().block_on(async move {
-                    let _ = event_sender.send(AppAction::ToggleOverlay(new_state, overlay.clone())).await;
+                    let _ = event_sender.send(AppAction::ToggleOverlay(new_state, overlay.clone(), true)).await;
                 });
                 Inhibit(true)
             })
@@ -0,0 +1,137 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::Duration;
+
+use gdk::RGBA;
+use glib::SourceId;
+use gtk::prelude::*;
+use webkit2gtk::{traits::{UserContentManagerExt, WebViewExt}, UserContentManager, WebView};
+
+use crate::overlay::page_bridge;
+
+/// Size of the area the overlay is scaled down into
+const PREVIEW_WIDTH: i32 = 320;
+const PREVIEW_HEIGHT: i32 = 240;
+/// The url is loaded once the typing stopped for a while
+const LOAD_DELAY: Duration = Duration::from_millis(600);
+const CHECKER_SIZE: f64 = 8.0;
+
+/// Renders the overlay of the form in the details pane, over a checkerboard showing its transparent parts
+pub struct OverlayPreview {
+    pub container: gtk::Box,
+    area: gtk::Overlay,
+    webview: WebView,
+    content_manager: UserContentManager,
+    user_css: RefCell<String>,
+    loaded_url: Rc<RefCell<String>>,
+    load_timer: Rc<RefCell<Option<SourceId>>>
+}
+
+impl OverlayPreview {
+    pub fn new() -> Self {
+        let container = gtk::Box::builder()
+            .orientation(gtk::Orientation::Vertical)
+            .spacing(10)
+            .margin_top(30)
+            .margin_start(20)
+            .valign(gtk::Align::Start)
+            .build();
+
+        let checkerboard = gtk::DrawingArea::new();
+        checkerboard.connect_draw(|area, ctx| {
+            let (width, height) = (area.allocated_width() as f64, area.allocated_height() as f64);
+            ctx.set_source_rgb(0.8, 0.8, 0.8);
+            let _ = ctx.paint();
+
+            ctx.set_source_rgb(0.6, 0.6, 0.6);
+            let (columns, rows) = ((width / CHECKER_SIZE).ceil() as i32, (height / CHECKER_SIZE).ceil() as i32);
+            for row in 0..rows {
+                for column in (row % 2..columns).step_by(2) {
+                    ctx.rectangle(column as f64 * CHECKER_SIZE, row as f64 * CHECKER_SIZE, CHECKER_SIZE, CHECKER_SIZE);
+                }
+            }
+            let _ = ctx.fill();
+            Inhibit(false)
+        });
+
+        let content_manager = UserContentManager::new();
+        let webview = WebView::builder().user_content_manager(&content_manager).build();
+        webview.set_background_color(&RGBA::new(0.0, 0.0, 0.0, 0.0));
+
+        // The checkerboard takes the scaled size of the overlay, the page is drawn over it
+        let area = gtk::Overlay::builder()
+            .halign(gtk::Align::Center)
+            .valign(gtk::Align::Center)
+            .build();
+        area.add(&checkerboard);
+        area.add_overlay(&webview);
+
+        let frame = gtk::Frame::new(None);
+        let frame_box = gtk::Box::new(gtk::Orientation::Vertical, 0);
+        frame_box.set_size_request(PREVIEW_WIDTH, PREVIEW_HEIGHT);
+        frame_box.pack_start(&area, true, false, 0);
+        frame.add(&frame_box);
+
+        let title = gtk::Label::builder().label("Preview").xalign(0.0).build();
+        container.add(&title);
+        container.add(&frame);
+
+        let preview = Self {
+            container,
+            area,
+            webview,
+            content_manager,
+            user_css: RefCell::new(String::new()),
+            loaded_url: Rc::new(RefCell::new(String::new())),
+            load_timer: Rc::new(RefCell::new(None))
+        };
+        preview.set_size(PREVIEW_WIDTH, PREVIEW_HEIGHT, 1.0);
+        preview
+    }
+
+    /// Show the page at the size and zoom of the overlay with its style sheet, an empty url clears the preview
+    pub fn update(&self, url: String, width: i32, height: i32, zoom: f64, user_css: &str) {
+        self.set_size(width, height, zoom);
+        self.set_user_css(user_css);
+
+        if *self.loaded_url.borrow() == url {
+            return;
+        }
+
+        if let Some(load_timer) = self.load_timer.borrow_mut().take() {
+            load_timer.remove();
+        }
+
+        let (webview, loaded_url, load_timer) = (self.webview.clone(), self.loaded_url.clone(), self.load_timer.clone());
+        *self.load_timer.borrow_mut() = Some(glib::timeout_add_local_once(LOAD_DELAY, move || {
+            load_timer.borrow_mut().take();
+            webview.load_uri(if url.is_empty() { "about:blank" } else { &url });
+            *loaded_url.borrow_mut() = url;
+        }));
+    }
+
+    /// Scale the overlay down to fit in the preview, the page keeps the layout of its real size and zoom
+    fn set_size(&self, width: i32, height: i32, zoom: f64) {
+        let (width, height) = (width.max(1), height.max(1));
+        let scale = (PREVIEW_WIDTH as f64 / width as f64)
+            .min(PREVIEW_HEIGHT as f64 / height as f64)
+            .min(1.0);
+
+        self.webview.set_zoom_level(scale * zoom);
+        self.area.set_size_request(
+            (width as f64 * scale).round() as i32,
+            (height as f64 * scale).round() as i32
+        );
+    }
+
+    /// The style sheets apply to the displayed page right away
+    fn set_user_css(&self, user_css: &str) {
+        if *self.user_css.borrow() == user_css {
+            return;
+        }
+
+        self.content_manager.remove_all_style_sheets();
+        page_bridge::add_user_css(&self.content_manager, user_css);
+        *self.user_css.borrow_mut() = user_css.to_string();
+    }
+}
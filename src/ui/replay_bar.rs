@@ -0,0 +1,230 @@
+use std::time::Duration;
+
+use async_channel::Sender;
+use glib::SignalHandlerId;
+use gtk::prelude::*;
+
+use crate::app::AppAction;
+use crate::app_config::AppConfig;
+use crate::relay::EventSourceStatus;
+use crate::relay::replay::REPLAY_SPEEDS;
+use crate::relay::synthetic::SyntheticConfig;
+
+pub struct ReplayBar {
+    pub container: gtk::Box,
+    record_button: gtk::ToggleButton,
+    recording_chooser: gtk::FileChooserButton,
+    synthetic_button: gtk::MenuButton,
+    pause_button: gtk::ToggleButton,
+    speed_combo: gtk::ComboBoxText,
+    seek_scale: gtk::Scale,
+    position_label: gtk::Label,
+    live_button: gtk::Button,
+
+    record_handler_id: Option<SignalHandlerId>,
+    pause_handler_id: Option<SignalHandlerId>
+}
+
+impl ReplayBar {
+    pub fn new(event_sender: Sender<AppAction>) -> Self {
+        let container = gtk::Box::new(gtk::Orientation::Horizontal, 10);
+        container.style_context().add_class("replay-bar");
+
+        let recording_chooser = gtk::FileChooserButton::new("Open a recording", gtk::FileChooserAction::Open);
+        recording_chooser.set_current_folder(AppConfig::default().recordings_path());
+
+        let speed_combo = gtk::ComboBoxText::new();
+        for speed in REPLAY_SPEEDS.iter() {
+            speed_combo.append_text(&format!("{}x", speed));
+        }
+        speed_combo.set_active(Some(0));
+
+        let seek_scale = gtk::Scale::with_range(gtk::Orientation::Horizontal, 0.0, 1.0, 1.0);
+        seek_scale.set_draw_value(false);
+        seek_scale.set_hexpand(true);
+
+        let mut replay_bar = Self {
+            container,
+            record_button: gtk::ToggleButton::with_label("Record"),
+            recording_chooser,
+            synthetic_button: gtk::MenuButton::builder().label("Simulate").build(),
+            pause_button: gtk::ToggleButton::with_label("Pause"),
+            speed_combo,
+            seek_scale,
+            position_label: gtk::Label::new(None),
+            live_button: gtk::Button::with_label("Back to live"),
+
+            record_handler_id: None,
+            pause_handler_id: None
+        };
+
+        replay_bar.container.add(&replay_bar.record_button);
+        replay_bar.container.add(&replay_bar.recording_chooser);
+        replay_bar.container.add(&replay_bar.synthetic_button);
+        replay_bar.container.add(&replay_bar.pause_button);
+        replay_bar.container.add(&replay_bar.speed_combo);
+        replay_bar.container.add(&replay_bar.seek_scale);
+        replay_bar.container.add(&replay_bar.position_label);
+        replay_bar.container.add(&replay_bar.live_button);
+
+        replay_bar.synthetic_button.set_popover(Some(&ReplayBar::create_synthetic_popover(event_sender.clone())));
+        replay_bar.setup_signals(event_sender);
+        replay_bar.set_source_status(EventSourceStatus::Live);
+
+        replay_bar
+    }
+
+    fn create_synthetic_popover(event_sender: Sender<AppAction>) -> gtk::Popover {
+        let default_config = SyntheticConfig::default();
+
+        let form_box = gtk::Box::new(gtk::Orientation::Vertical, 10);
+        form_box.set_margin(10);
+
+        let party_entry = gtk::Entry::new();
+        party_entry.set_text(&default_config.party.join(", "));
+        party_entry.set_width_chars(40);
+        let length_spin = gtk::SpinButton::with_range(10.0, 3600.0, 10.0);
+        length_spin.set_value(default_config.encounter_length.as_secs() as f64);
+        let dps_spin = gtk::SpinButton::with_range(100.0, 200000.0, 500.0);
+        dps_spin.set_value(default_config.base_dps);
+        let spread_spin = gtk::SpinButton::with_range(0.0, 100.0, 5.0);
+        spread_spin.set_value(default_config.dps_spread * 100.0);
+
+        form_box.add(&ReplayBar::create_form_row("Party jobs", &party_entry));
+        form_box.add(&ReplayBar::create_form_row("Length (s)", &length_spin));
+        form_box.add(&ReplayBar::create_form_row("Average DPS", &dps_spin));
+        form_box.add(&ReplayBar::create_form_row("DPS spread (%)", &spread_spin));
+
+        let start_button = gtk::Button::with_label("Start");
+        form_box.add(&start_button);
+        form_box.show_all();
+
+        let popover = gtk::Popover::new(None::<&gtk::Widget>);
+        popover.add(&form_box);
+
+        let popover_clone = popover.clone();
+        start_button.connect_clicked(move |_| {
+            let party: Vec<String> = party_entry.text()
+                .split(',')
+                .map(|job| job.trim().to_uppercase())
+                .filter(|job| !job.is_empty())
+                .collect();
+
+            if party.is_empty() || !party.iter().all(|job| SyntheticConfig::is_known_job(job)) {
+                party_entry.style_context().add_class("error");
+                return;
+            }
+            party_entry.style_context().remove_class("error");
+
+            let config = SyntheticConfig {
+                party,
+                encounter_length: Duration::from_secs(length_spin.value_as_int() as u64),
+                base_dps: dps_spin.value(),
+                dps_spread: spread_spin.value() / 100.0
+            };
+            let _ = glib::MainContext::default().block_on(event_sender.send(AppAction::StartSynthetic(config)));
+            popover_clone.popdown();
+        });
+
+        popover
+    }
+
+    fn create_form_row(name: &str, widget: &impl IsA<gtk::Widget>) -> gtk::Box {
+        let form_box = gtk::Box::new(gtk::Orientation::Horizontal, 0);
+
+        let label = gtk::Label::new(Some(name));
+        label.set_size_request(120, 30);
+        label.set_xalign(0.0);
+        form_box.add(&label);
+        widget.set_hexpand(true);
+        form_box.add(widget);
+
+        form_box
+    }
+
+    fn setup_signals(&mut self, event_sender: Sender<AppAction>) {
+        let event_sender_clone = event_sender.clone();
+        self.record_handler_id = Some(self.record_button.connect_toggled(move |button| {
+            let action = if button.is_active() { AppAction::StartRecording } else { AppAction::StopRecording };
+            let _ = glib::MainContext::default().block_on(event_sender_clone.send(action));
+        }));
+
+        let event_sender_clone = event_sender.clone();
+        self.recording_chooser.connect_file_set(move |chooser| {
+            if let Some(path) = chooser.filename() {
+                let _ = glib::MainContext::default().block_on(event_sender_clone.send(AppAction::StartReplay(path)));
+            }
+        });
+
+        let event_sender_clone = event_sender.clone();
+        self.pause_handler_id = Some(self.pause_button.connect_toggled(move |button| {
+            let _ = glib::MainContext::default().block_on(event_sender_clone.send(AppAction::PauseReplay(button.is_active())));
+        }));
+
+        let event_sender_clone = event_sender.clone();
+        self.speed_combo.connect_changed(move |combo| {
+            let speed = combo.active()
+                .and_then(|index| REPLAY_SPEEDS.get(index as usize))
+                .copied()
+                .unwrap_or(1.0);
+            let _ = glib::MainContext::default().block_on(event_sender_clone.send(AppAction::SetReplaySpeed(speed)));
+        });
+
+        // Only emitted when the user moves the slider, not by set_value
+        let event_sender_clone = event_sender.clone();
+        self.seek_scale.connect_change_value(move |_, _, value| {
+            let position = Duration::from_secs_f64(value.max(0.0));
+            let _ = glib::MainContext::default().block_on(event_sender_clone.send(AppAction::SeekReplay(position)));
+            Inhibit(false)
+        });
+
+        self.live_button.connect_clicked(move |_| {
+            let _ = glib::MainContext::default().block_on(event_sender.send(AppAction::UseLiveEvents));
+        });
+    }
+
+    pub fn set_recording(&self, is_recording: bool) {
+        if let Some(handler_id) = &self.record_handler_id {
+            self.record_button.block_signal(handler_id);
+            self.record_button.set_active(is_recording);
+            self.record_button.unblock_signal(handler_id);
+        }
+    }
+
+    pub fn set_source_status(&self, status: EventSourceStatus) {
+        let is_replaying = matches!(status, EventSourceStatus::Replay(_));
+        self.pause_button.set_sensitive(is_replaying);
+        self.speed_combo.set_sensitive(is_replaying);
+        self.seek_scale.set_sensitive(is_replaying);
+        self.live_button.set_sensitive(status != EventSourceStatus::Live);
+
+        let status = match status {
+            EventSourceStatus::Replay(status) => status,
+            other_status => {
+                self.seek_scale.set_value(0.0);
+                self.position_label.set_text(if other_status == EventSourceStatus::Synthetic { "Synthetic" } else { "Live" });
+                self.recording_chooser.unselect_all();
+                return;
+            }
+        };
+
+        self.seek_scale.set_range(0.0, status.duration.as_secs_f64().max(1.0));
+        self.seek_scale.set_value(status.position.as_secs_f64());
+        self.position_label.set_text(&format!(
+            "{} / {}",
+            format_duration(status.position),
+            format_duration(status.duration)
+        ));
+
+        if let Some(handler_id) = &self.pause_handler_id {
+            self.pause_button.block_signal(handler_id);
+            self.pause_button.set_active(status.is_paused);
+            self.pause_button.unblock_signal(handler_id);
+        }
+    }
+}
+
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    format!("{:02}:{:02}", seconds / 60, seconds % 60)
+}
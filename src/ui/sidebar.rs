@@ -1,90 +1,541 @@
+use std::cell::{Cell, RefCell};
+use std::cmp::Ordering;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::rc::Rc;
+use std::time::UNIX_EPOCH;
+
 use async_channel::Sender;
 use gtk::prelude::*;
 use gtk::{traits::{TreeViewExt, WidgetExt}, prelude::TreeStoreExtManual};
 
 use crate::app::AppAction;
-use crate::layout_config::get_layout_by_name;
+use crate::layout_config::{get_layout_by_name, overlay_modified_time, load_created_folders, save_created_folders};
 use crate::layout_config::LayoutConfig;
+use crate::overlay::OverlayStatus;
+
+const NAME_COLUMN: u32 = 0;
+const STATUS_ICON_COLUMN: u32 = 1;
+const STATUS_TOOLTIP_COLUMN: u32 = 2;
+/// Full path of the folder rows, empty for the overlay rows
+const FOLDER_PATH_COLUMN: u32 = 3;
+const URL_COLUMN: u32 = 4;
+const TAGS_COLUMN: u32 = 5;
+/// Seconds since the epoch
+const MODIFIED_COLUMN: u32 = 6;
+const ACTIVE_COLUMN: u32 = 7;
+const FAILED_COLUMN: u32 = 8;
+
+/// Overlays are only dragged inside the sidebar
+const OVERLAY_DRAG_TARGET: &str = "xiv-overlay/overlay-name";
+
+/// Which overlays the sidebar lists, their folders are kept
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+enum OverlayFilter {
+    #[default]
+    All,
+    Active,
+    Failing
+}
+
+impl OverlayFilter {
+    const ALL: [OverlayFilter; 3] = [
+        OverlayFilter::All,
+        OverlayFilter::Active,
+        OverlayFilter::Failing,
+    ];
+
+    fn label(&self) -> &'static str {
+        match self {
+            OverlayFilter::All => "All overlays",
+            OverlayFilter::Active => "Active overlays",
+            OverlayFilter::Failing => "Failing overlays",
+        }
+    }
+}
+
+/// How the overlays of a folder are sorted, the folders always come first by name
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+enum SortOrder {
+    #[default]
+    Name,
+    LastModified,
+    ActiveFirst
+}
+
+impl SortOrder {
+    const ALL: [SortOrder; 3] = [
+        SortOrder::Name,
+        SortOrder::LastModified,
+        SortOrder::ActiveFirst,
+    ];
+
+    fn label(&self) -> &'static str {
+        match self {
+            SortOrder::Name => "By name",
+            SortOrder::LastModified => "Last modified first",
+            SortOrder::ActiveFirst => "Active first",
+        }
+    }
+}
+
+/// The values of a row the filter and the sort look at
+#[derive(Debug, Default, Clone)]
+struct RowInfo {
+    name: String,
+    url: String,
+    tags: String,
+    is_folder: bool,
+    is_active: bool,
+    has_failed: bool,
+    /// Seconds since the epoch
+    modified_time: i64
+}
+
+impl RowInfo {
+    fn read(model: &gtk::TreeModel, iter: &gtk::TreeIter) -> Self {
+        let text = |column: u32| model.value(iter, column as i32).get::<String>().unwrap_or_default();
+        let flag = |column: u32| model.value(iter, column as i32).get::<bool>().unwrap_or(false);
+
+        Self {
+            name: text(NAME_COLUMN),
+            url: text(URL_COLUMN),
+            tags: text(TAGS_COLUMN),
+            is_folder: Sidebar::folder_path(model, iter).is_some(),
+            is_active: flag(ACTIVE_COLUMN),
+            has_failed: flag(FAILED_COLUMN),
+            modified_time: model.value(iter, MODIFIED_COLUMN as i32).get::<i64>().unwrap_or(0)
+        }
+    }
+}
 
 pub struct Sidebar {
     pub frame: gtk::Frame,
     treeview: gtk::TreeView,
-    
+    active_toggle: gtk::CellRendererToggle,
+    /// The overlays and folders, displayed through a filter then a sort model
+    store: gtk::TreeStore,
+    filter_model: gtk::TreeModelFilter,
+    search_entry: gtk::SearchEntry,
+    filter_combo: gtk::ComboBoxText,
+    sort_combo: gtk::ComboBoxText,
+    search_text: Rc<RefCell<String>>,
+    overlay_filter: Rc<Cell<OverlayFilter>>,
+    sort_order: Rc<Cell<SortOrder>>,
+    /// Folders created in the sidebar, kept while they do not contain any overlay yet
+    created_folders: Rc<RefCell<BTreeSet<String>>>,
+
     add_menu_item: gtk::MenuItem,
+    new_folder_menu_item: gtk::MenuItem,
+    reload_menu_item: gtk::MenuItem,
+    inspect_menu_item: gtk::MenuItem,
+    duplicate_menu_item: gtk::MenuItem,
     remove_menu_item: gtk::MenuItem,
+    new_subfolder_menu_item: gtk::MenuItem,
+    enable_folder_menu_item: gtk::MenuItem,
+    disable_folder_menu_item: gtk::MenuItem,
 }
 
 impl Sidebar {
     pub fn new(event_sender: Sender<AppAction>) -> Self {
         let sidebar_frame = gtk::Frame::new(None);
         sidebar_frame.set_size_request(200, 700);
-    
-        let treeview = gtk::TreeView::new();
+
+        let store = gtk::TreeStore::new(&[
+            String::static_type(),
+            String::static_type(),
+            String::static_type(),
+            String::static_type(),
+            String::static_type(),
+            String::static_type(),
+            i64::static_type(),
+            bool::static_type(),
+            bool::static_type()
+        ]);
+        let filter_model = gtk::TreeModelFilter::new(&store, None);
+        let sort_model = gtk::TreeModelSort::new(&filter_model);
+
+        let treeview = gtk::TreeView::with_model(&sort_model);
         treeview.set_headers_visible(false);
         treeview.set_activate_on_single_click(true);
-        Sidebar::append_treeview_column(&treeview, 0);
-        sidebar_frame.add(&treeview);
+        treeview.set_tooltip_column(STATUS_TOOLTIP_COLUMN as i32);
+        let active_toggle = Sidebar::append_active_column(&treeview, ACTIVE_COLUMN as i32);
+        Sidebar::append_status_column(&treeview, STATUS_ICON_COLUMN as i32);
+        Sidebar::append_treeview_column(&treeview, NAME_COLUMN as i32);
+        Sidebar::enable_overlay_drag(&treeview);
+
+        let search_entry = gtk::SearchEntry::builder()
+            .placeholder_text("Search by name, url or tag")
+            .build();
+        let filter_combo = gtk::ComboBoxText::new();
+        for overlay_filter in OverlayFilter::ALL.iter() {
+            filter_combo.append_text(overlay_filter.label());
+        }
+        filter_combo.set_active(Some(0));
+        let sort_combo = gtk::ComboBoxText::new();
+        for sort_order in SortOrder::ALL.iter() {
+            sort_combo.append_text(sort_order.label());
+        }
+        sort_combo.set_active(Some(0));
+
+        let options_box = gtk::Box::new(gtk::Orientation::Horizontal, 5);
+        options_box.pack_start(&filter_combo, true, true, 0);
+        options_box.pack_start(&sort_combo, true, true, 0);
+
+        let sidebar_box = gtk::Box::builder()
+            .orientation(gtk::Orientation::Vertical)
+            .spacing(5)
+            .margin(5)
+            .build();
+        sidebar_box.pack_start(&search_entry, false, false, 0);
+        sidebar_box.pack_start(&options_box, false, false, 0);
+        sidebar_box.pack_start(&treeview, true, true, 0);
+        sidebar_frame.add(&sidebar_box);
 
         let item_actions_menu = gtk::Menu::new();
+        let reload_menu_item = gtk::MenuItem::with_label("Reload");
+        let inspect_menu_item = gtk::MenuItem::with_label("Open inspector");
+        let duplicate_menu_item = gtk::MenuItem::with_label("Duplicate");
         let remove_menu_item = gtk::MenuItem::with_label("Delete");
+        item_actions_menu.append(&reload_menu_item);
+        item_actions_menu.append(&inspect_menu_item);
+        item_actions_menu.append(&gtk::SeparatorMenuItem::new());
+        item_actions_menu.append(&duplicate_menu_item);
         item_actions_menu.append(&remove_menu_item);
-        remove_menu_item.show();
+        item_actions_menu.show_all();
+
+        let folder_actions_menu = gtk::Menu::new();
+        let new_subfolder_menu_item = gtk::MenuItem::with_label("New folder");
+        let enable_folder_menu_item = gtk::MenuItem::with_label("Enable all");
+        let disable_folder_menu_item = gtk::MenuItem::with_label("Disable all");
+        folder_actions_menu.append(&new_subfolder_menu_item);
+        folder_actions_menu.append(&enable_folder_menu_item);
+        folder_actions_menu.append(&disable_folder_menu_item);
+        folder_actions_menu.show_all();
 
         let treeview_actions_menu = gtk::Menu::new();
         let add_menu_item = gtk::MenuItem::with_label("Add new config");
+        let new_folder_menu_item = gtk::MenuItem::with_label("New folder");
         treeview_actions_menu.append(&add_menu_item);
-        add_menu_item.show();
+        treeview_actions_menu.append(&new_folder_menu_item);
+        treeview_actions_menu.show_all();
 
         let sidebar = Self {
             frame: sidebar_frame,
             treeview,
+            active_toggle,
+            store,
+            filter_model,
+            search_entry,
+            filter_combo,
+            sort_combo,
+            search_text: Rc::new(RefCell::new(String::new())),
+            overlay_filter: Rc::new(Cell::new(OverlayFilter::default())),
+            sort_order: Rc::new(Cell::new(SortOrder::default())),
+            created_folders: Rc::new(RefCell::new(load_created_folders())),
 
             add_menu_item,
-            remove_menu_item
+            new_folder_menu_item,
+            reload_menu_item,
+            inspect_menu_item,
+            duplicate_menu_item,
+            remove_menu_item,
+            new_subfolder_menu_item,
+            enable_folder_menu_item,
+            disable_folder_menu_item
         };
 
-        sidebar.setup_signals(treeview_actions_menu, item_actions_menu, event_sender);
+        sidebar.setup_model(&sort_model);
+        sidebar.setup_signals(treeview_actions_menu, item_actions_menu, folder_actions_menu, event_sender);
 
         sidebar
     }
 
-    fn setup_signals(&self, treeview_menu: gtk::Menu, treeview_item_menu: gtk::Menu, event_sender: Sender<AppAction>) {
+    fn setup_model(&self, sort_model: &gtk::TreeModelSort) {
+        let (search_text, overlay_filter) = (self.search_text.clone(), self.overlay_filter.clone());
+        self.filter_model.set_visible_func(move |model, iter| {
+            Sidebar::is_row_visible(model, iter, &search_text.borrow(), overlay_filter.get())
+        });
+
+        let sort_order = self.sort_order.clone();
+        sort_model.set_default_sort_func(move |model, first, second| {
+            Sidebar::compare_rows(&RowInfo::read(model, first), &RowInfo::read(model, second), sort_order.get())
+        });
+
+        let (search_text, filter_model, treeview) = (self.search_text.clone(), self.filter_model.clone(), self.treeview.clone());
+        self.search_entry.connect_search_changed(move |entry| {
+            *search_text.borrow_mut() = entry.text().to_lowercase();
+            filter_model.refilter();
+            // The matches are shown wherever they are
+            if !search_text.borrow().is_empty() {
+                treeview.expand_all();
+            }
+        });
+
+        let (overlay_filter, filter_model) = (self.overlay_filter.clone(), self.filter_model.clone());
+        self.filter_combo.connect_changed(move |combo| {
+            let selected_filter = combo.active().and_then(|index| OverlayFilter::ALL.get(index as usize)).copied();
+            overlay_filter.set(selected_filter.unwrap_or_default());
+            filter_model.refilter();
+        });
+
+        let (sort_order, sort_model) = (self.sort_order.clone(), sort_model.clone());
+        self.sort_combo.connect_changed(move |combo| {
+            let selected_order = combo.active().and_then(|index| SortOrder::ALL.get(index as usize)).copied();
+            sort_order.set(selected_order.unwrap_or_default());
+            // Setting the sort function again sorts the rows with the new order
+            let sort_order = sort_order.clone();
+            sort_model.set_default_sort_func(move |model, first, second| {
+                Sidebar::compare_rows(&RowInfo::read(model, first), &RowInfo::read(model, second), sort_order.get())
+            });
+        });
+    }
+
+    /// A folder is shown while one of its overlays is, or when nothing is filtered
+    fn is_row_visible(model: &gtk::TreeModel, iter: &gtk::TreeIter, search_text: &str, overlay_filter: OverlayFilter) -> bool {
+        if Sidebar::folder_path(model, iter).is_none() {
+            return Sidebar::is_overlay_matching(&RowInfo::read(model, iter), search_text, overlay_filter);
+        }
+        if search_text.is_empty() && overlay_filter == OverlayFilter::All {
+            return true;
+        }
+
+        let Some(child) = model.iter_children(Some(iter)) else {
+            return false;
+        };
+        loop {
+            if Sidebar::is_row_visible(model, &child, search_text, overlay_filter) {
+                return true;
+            }
+            if !model.iter_next(&child) {
+                return false;
+            }
+        }
+    }
+
+    /// `search_text` is lowercase, it is looked for in the name, url and tags
+    fn is_overlay_matching(row: &RowInfo, search_text: &str, overlay_filter: OverlayFilter) -> bool {
+        let is_filtered_in = match overlay_filter {
+            OverlayFilter::All => true,
+            OverlayFilter::Active => row.is_active,
+            OverlayFilter::Failing => row.has_failed
+        };
+        if !is_filtered_in {
+            return false;
+        }
+
+        search_text.is_empty() || [&row.name, &row.url, &row.tags]
+            .iter()
+            .any(|text| text.to_lowercase().contains(search_text))
+    }
+
+    fn compare_rows(first: &RowInfo, second: &RowInfo, sort_order: SortOrder) -> Ordering {
+        let by_order = match sort_order {
+            _ if first.is_folder || second.is_folder => Ordering::Equal,
+            SortOrder::Name => Ordering::Equal,
+            SortOrder::LastModified => second.modified_time.cmp(&first.modified_time),
+            SortOrder::ActiveFirst => second.is_active.cmp(&first.is_active)
+        };
+
+        second.is_folder.cmp(&first.is_folder)
+            .then(by_order)
+            .then_with(|| first.name.to_lowercase().cmp(&second.name.to_lowercase()))
+    }
+
+    fn setup_signals(
+        &self,
+        treeview_menu: gtk::Menu,
+        treeview_item_menu: gtk::Menu,
+        treeview_folder_menu: gtk::Menu,
+        event_sender: Sender<AppAction>
+    ) {
         let event_sender_clone = event_sender.clone();
         self.add_menu_item.connect_activate(move |_item| {
             let _ = glib::MainContext::default().block_on(event_sender_clone.send(AppAction::NewOverlay));
         });
-        
+
+        let (treeview, store, created_folders) = (self.treeview.clone(), self.store.clone(), self.created_folders.clone());
+        self.new_folder_menu_item.connect_activate(move |_item| {
+            Sidebar::ask_new_folder(&treeview, &store, created_folders.clone(), None);
+        });
+
+        let (treeview, store, created_folders) = (self.treeview.clone(), self.store.clone(), self.created_folders.clone());
+        self.new_subfolder_menu_item.connect_activate(move |_item| {
+            let parent_folder = Sidebar::cursor_folder(&treeview);
+            Sidebar::ask_new_folder(&treeview, &store, created_folders.clone(), parent_folder);
+        });
+
+        for (menu_item, new_state) in [(&self.enable_folder_menu_item, true), (&self.disable_folder_menu_item, false)] {
+            let event_sender_clone = event_sender.clone();
+            let treeview = self.treeview.clone();
+            menu_item.connect_activate(move |_item| {
+                if let Some(folder) = Sidebar::cursor_folder(&treeview) {
+                    let _ = glib::MainContext::default().block_on(event_sender_clone.send(AppAction::ToggleFolder(new_state, folder)));
+                }
+            });
+        }
+
         let event_sender_clone = event_sender.clone();
         let treeview = self.treeview.clone();
-        self.remove_menu_item.connect_activate(move |_item| {
-            let (path, _) = treeview.cursor();
+        self.active_toggle.connect_toggled(move |_toggle, path| {
             let treeview_model = treeview.model().unwrap();
-            let iter = treeview_model.iter(&path.unwrap()).unwrap();
-            let value = treeview_model.value(&iter, 0).get::<String>().unwrap();
+            let Some(iter) = treeview_model.iter(&path) else {
+                return;
+            };
+            let value = treeview_model.value(&iter, NAME_COLUMN as i32).get::<String>().unwrap();
+            let is_active = treeview_model.value(&iter, ACTIVE_COLUMN as i32).get::<bool>().unwrap_or(false);
 
             if let Ok(overlay) = get_layout_by_name(&value) {
+                let _ = glib::MainContext::default().block_on(event_sender_clone.send(AppAction::ToggleOverlay(!is_active, overlay, false)));
+            }
+        });
+
+        let event_sender_clone = event_sender.clone();
+        let treeview = self.treeview.clone();
+        self.reload_menu_item.connect_activate(move |_item| {
+            if let Some(overlay_name) = Sidebar::cursor_overlay(&treeview) {
+                let _ = glib::MainContext::default().block_on(event_sender_clone.send(AppAction::ReloadOverlay(overlay_name)));
+            }
+        });
+
+        let event_sender_clone = event_sender.clone();
+        let treeview = self.treeview.clone();
+        self.inspect_menu_item.connect_activate(move |_item| {
+            if let Some(overlay_name) = Sidebar::cursor_overlay(&treeview) {
+                let _ = glib::MainContext::default().block_on(event_sender_clone.send(AppAction::InspectOverlay(overlay_name)));
+            }
+        });
+
+        let event_sender_clone = event_sender.clone();
+        let treeview = self.treeview.clone();
+        self.duplicate_menu_item.connect_activate(move |_item| {
+            // The menu may be opened with the cursor on no row, or on a folder
+            let Some(overlay_name) = Sidebar::cursor_overlay(&treeview) else {
+                return;
+            };
+
+            if let Ok(overlay) = get_layout_by_name(&overlay_name) {
+                let _ = glib::MainContext::default().block_on(event_sender_clone.send(AppAction::DuplicateOverlay(overlay)));
+            }
+        });
+
+        let event_sender_clone = event_sender.clone();
+        let treeview = self.treeview.clone();
+        self.remove_menu_item.connect_activate(move |_item| {
+            // The menu may be opened with the cursor on no row, or on a folder
+            let Some(overlay_name) = Sidebar::cursor_overlay(&treeview) else {
+                return;
+            };
+
+            if let Ok(overlay) = get_layout_by_name(&overlay_name) {
                 let _ = glib::MainContext::default().block_on(event_sender_clone.send(AppAction::DeleteOverlay(overlay)));
                 let _ = glib::MainContext::default().block_on(event_sender_clone.send(AppAction::LoadOverlaysList));
             }
         });
 
+        let event_sender_clone = event_sender.clone();
         self.treeview.connect_row_activated(move |view, path, _column| {
             let model = view.model().unwrap();
             let iter = model.iter(path).unwrap();
-            let value = model.value(&iter, 0).get::<String>().unwrap();
-    
+
+            // A click on a folder opens or closes it
+            if Sidebar::folder_path(&model, &iter).is_some() {
+                if view.row_expanded(path) {
+                    view.collapse_row(path);
+                } else {
+                    view.expand_row(path, false);
+                }
+                return;
+            }
+
+            let value = model.value(&iter, NAME_COLUMN as i32).get::<String>().unwrap();
             if let Ok(overlay) = get_layout_by_name(&value) {
-                let _ = glib::MainContext::default().block_on(event_sender.send(AppAction::SelectOverlay(overlay)));
+                let _ = glib::MainContext::default().block_on(event_sender_clone.send(AppAction::SelectOverlay(overlay)));
             }
         });
 
+        // The dragged row is the one pressed, the cursor may still be on another row
+        let press_position: Rc<Cell<Option<(f64, f64)>>> = Rc::default();
+        let dragged_overlay: Rc<RefCell<Option<String>>> = Rc::default();
+        let press_position_cloned = press_position.clone();
+        self.treeview.connect_button_press_event(move |_, event| {
+            if event.button() == 1 {
+                press_position_cloned.set(Some(event.position()));
+            }
+            Inhibit(false)
+        });
+
+        let dragged_overlay_cloned = dragged_overlay.clone();
+        self.treeview.connect_drag_begin(move |treeview, _| {
+            // Folders are not dragged, they follow their overlays
+            *dragged_overlay_cloned.borrow_mut() = press_position.get()
+                .and_then(|(x, y)| treeview.path_at_pos(x as i32, y as i32))
+                .and_then(|(path, _, _, _)| path)
+                .zip(treeview.model())
+                .and_then(|(path, model)| model.iter(&path).map(|iter| (model, iter)))
+                .filter(|(model, iter)| Sidebar::folder_path(model, iter).is_none())
+                .and_then(|(model, iter)| model.value(&iter, NAME_COLUMN as i32).get::<String>().ok());
+        });
+
+        let dragged_overlay_cloned = dragged_overlay.clone();
+        self.treeview.connect_drag_end(move |_, _| {
+            dragged_overlay_cloned.borrow_mut().take();
+        });
+
+        self.treeview.connect_drag_data_get(move |treeview, _, selection_data, _, _| {
+            treeview.stop_signal_emission_by_name("drag-data-get");
+
+            let overlay_name = dragged_overlay.borrow().clone().unwrap_or_default();
+            selection_data.set(&selection_data.target(), 8, overlay_name.as_bytes());
+        });
+
+        // The filter and sort models can not receive rows, the drop is handled here instead of the treeview
+        self.treeview.connect_drag_drop(|treeview, context, _, _, time| {
+            treeview.stop_signal_emission_by_name("drag-drop");
+
+            match treeview.drag_dest_find_target(context, None) {
+                Some(target) => treeview.drag_get_data(context, &target, time),
+                None => context.drag_finish(false, false, time)
+            }
+            true
+        });
+
+        self.treeview.connect_drag_data_received(move |treeview, context, x, y, selection_data, _, time| {
+            treeview.stop_signal_emission_by_name("drag-data-received");
+
+            let overlay_name = String::from_utf8(selection_data.data()).unwrap_or_default();
+            if overlay_name.is_empty() {
+                context.drag_finish(false, false, time);
+                return;
+            }
+
+            // Below the last row is the top level
+            let folder = treeview.model()
+                .zip(treeview.dest_row_at_pos(x, y))
+                .and_then(|(model, (path, position))| Sidebar::drop_folder(&model, &path?, position));
+            let _ = glib::MainContext::default().block_on(event_sender.send(AppAction::MoveToFolder(overlay_name, folder)));
+            context.drag_finish(true, false, time);
+        });
+
+        let (reload_menu_item, inspect_menu_item) = (self.reload_menu_item.clone(), self.inspect_menu_item.clone());
         self.treeview.connect_button_press_event(move |treeview, event| {
             if event.button() == 3 {
                 let selected_item = treeview.path_at_pos(event.position().0 as i32, event.position().1 as i32)
-                    .map(|(path, _, _, _)| {
-                        treeview.set_cursor(&path.unwrap(), None::<&gtk::TreeViewColumn>, false);
+                    .and_then(|(path, _, _, _)| path)
+                    .map(|path| {
+                        treeview.set_cursor(&path, None::<&gtk::TreeViewColumn>, false);
                         treeview.grab_focus();
-                        treeview_item_menu.popup_at_pointer(Some(event));
+
+                        let is_folder = Sidebar::cursor_folder(treeview).is_some();
+                        // Only a displayed overlay has a page to reload or inspect
+                        let is_active = treeview.model()
+                            .and_then(|model| model.iter(&path).map(|iter| (model, iter)))
+                            .and_then(|(model, iter)| model.value(&iter, ACTIVE_COLUMN as i32).get::<bool>().ok())
+                            .unwrap_or(false);
+                        reload_menu_item.set_sensitive(is_active);
+                        inspect_menu_item.set_sensitive(is_active);
+
+                        let menu = if is_folder { &treeview_folder_menu } else { &treeview_item_menu };
+                        menu.popup_at_pointer(Some(event));
                     });
 
                 // If we do not find any item, show the other menu
@@ -99,19 +550,297 @@ impl Sidebar {
         });
     }
 
+    /// Let the overlays be dragged between the folders, highlighting the row they would be dropped on
+    fn enable_overlay_drag(treeview: &gtk::TreeView) {
+        let targets = [gtk::TargetEntry::new(OVERLAY_DRAG_TARGET, gtk::TargetFlags::SAME_WIDGET, 0)];
+        treeview.drag_source_set(gdk::ModifierType::BUTTON1_MASK, &targets, gdk::DragAction::MOVE);
+        // The drop is handled in drag-drop, nothing is done by default
+        treeview.drag_dest_set(gtk::DestDefaults::empty(), &targets, gdk::DragAction::MOVE);
+
+        treeview.connect_drag_motion(|treeview, context, x, y, time| {
+            match treeview.dest_row_at_pos(x, y) {
+                Some((path, position)) => treeview.set_drag_dest_row(path.as_ref(), position),
+                None => treeview.set_drag_dest_row(None, gtk::TreeViewDropPosition::Before)
+            }
+            context.drag_status(gdk::DragAction::MOVE, time);
+            true
+        });
+        treeview.connect_drag_leave(|treeview, _, _| {
+            treeview.set_drag_dest_row(None, gtk::TreeViewDropPosition::Before);
+        });
+    }
+
+    /// The folder an overlay dropped on a row goes in, the top level when `None`
+    fn drop_folder(model: &gtk::TreeModel, path: &gtk::TreePath, position: gtk::TreeViewDropPosition) -> Option<String> {
+        let iter = model.iter(path)?;
+        let is_into = matches!(position, gtk::TreeViewDropPosition::IntoOrBefore | gtk::TreeViewDropPosition::IntoOrAfter);
+
+        match Sidebar::folder_path(model, &iter) {
+            Some(folder) if is_into => Some(folder),
+            // Next to a row is in the folder of that row
+            _ => model.iter_parent(&iter).and_then(|parent| Sidebar::folder_path(model, &parent))
+        }
+    }
+
+    fn folder_path(model: &gtk::TreeModel, iter: &gtk::TreeIter) -> Option<String> {
+        model.value(iter, FOLDER_PATH_COLUMN as i32)
+            .get::<String>()
+            .ok()
+            .filter(|folder| !folder.is_empty())
+    }
+
+    /// The name of the overlay under the cursor, `None` when the cursor is on a folder
+    fn cursor_overlay(treeview: &gtk::TreeView) -> Option<String> {
+        let (path, _) = treeview.cursor();
+        let model = treeview.model()?;
+        let iter = model.iter(&path?)?;
+        if Sidebar::folder_path(&model, &iter).is_some() {
+            return None;
+        }
+        model.value(&iter, NAME_COLUMN as i32).get::<String>().ok()
+    }
+
+    /// The folder under the cursor, `None` when the cursor is on an overlay
+    fn cursor_folder(treeview: &gtk::TreeView) -> Option<String> {
+        let (path, _) = treeview.cursor();
+        let model = treeview.model()?;
+        let iter = model.iter(&path?)?;
+        Sidebar::folder_path(&model, &iter)
+    }
+
+    fn ask_new_folder(
+        treeview: &gtk::TreeView,
+        store: &gtk::TreeStore,
+        created_folders: Rc<RefCell<BTreeSet<String>>>,
+        parent_folder: Option<String>
+    ) {
+        let dialog = gtk::Dialog::builder()
+            .title("New folder")
+            .modal(true)
+            .build();
+        if let Some(window) = treeview.toplevel().and_then(|toplevel| toplevel.downcast::<gtk::Window>().ok()) {
+            dialog.set_transient_for(Some(&window));
+        }
+        dialog.add_buttons(&[("Cancel", gtk::ResponseType::Cancel), ("Create", gtk::ResponseType::Accept)]);
+        dialog.set_default_response(gtk::ResponseType::Accept);
+
+        let name_entry = gtk::Entry::builder()
+            .placeholder_text("Folder name")
+            .activates_default(true)
+            .margin(10)
+            .build();
+        dialog.content_area().add(&name_entry);
+        dialog.show_all();
+
+        let (treeview, store) = (treeview.clone(), store.clone());
+        dialog.connect_response(move |dialog, response| {
+            // Slashes separate the nested folders
+            let name = name_entry.text().replace('/', "-").trim().to_string();
+            if response == gtk::ResponseType::Accept && !name.is_empty() {
+                let folder = match &parent_folder {
+                    Some(parent_folder) => format!("{}/{}", parent_folder, name),
+                    None => name
+                };
+                created_folders.borrow_mut().insert(folder.clone());
+                // Kept across restarts, even while no overlay is in it
+                if let Err(error) = save_created_folders(&created_folders.borrow()) {
+                    error!("The folders could not be saved: {}", error);
+                }
+
+                Sidebar::folder_iter(&store, &mut Sidebar::folder_iters(&store), &folder);
+                if let Some(parent_folder) = &parent_folder {
+                    Sidebar::expand_folder(&treeview, parent_folder);
+                }
+            }
+            dialog.close();
+        });
+    }
+
+    fn expand_folder(treeview: &gtk::TreeView, folder: &str) {
+        let Some(model) = treeview.model() else {
+            return;
+        };
+
+        model.foreach(|model, path, iter| {
+            let is_folder = Sidebar::folder_path(model, iter).as_deref() == Some(folder);
+            if is_folder {
+                treeview.expand_to_path(path);
+            }
+            is_folder
+        });
+    }
+
     pub fn display_overlays_list(&self, overlays_list: Vec<LayoutConfig>) {
-        let model = Sidebar::create_treeview_entries(overlays_list);
-        self.treeview.set_model(Some(&model));
+        // Keep the folders as they were opened, the new ones are open
+        let mut known_folders = HashSet::new();
+        let mut expanded_folders = HashSet::new();
+        if let Some(old_model) = self.treeview.model() {
+            old_model.foreach(|model, _, iter| {
+                known_folders.extend(Sidebar::folder_path(model, iter));
+                false
+            });
+            self.treeview.map_expanded_rows(|_, path| {
+                if let Some(iter) = old_model.iter(path) {
+                    expanded_folders.extend(Sidebar::folder_path(&old_model, &iter));
+                }
+            });
+        }
+
+        self.store.clear();
+        Sidebar::fill_store(&self.store, overlays_list, &self.created_folders.borrow());
+
+        let Some(model) = self.treeview.model() else {
+            return;
+        };
+        model.foreach(|model, path, iter| {
+            if let Some(folder) = Sidebar::folder_path(model, iter) {
+                if !known_folders.contains(&folder) || expanded_folders.contains(&folder) {
+                    self.treeview.expand_row(path, false);
+                }
+            }
+            false
+        });
     }
 
-    fn create_treeview_entries(overlays_list: Vec<LayoutConfig>) -> gtk::TreeStore {
-        // Creation of a model with two rows.
-        let model = gtk::TreeStore::new(&[String::static_type()]);
+    /// Move the cursor to the row of an overlay, opening its folders
+    pub fn select(&self, overlay_name: &str) {
+        let Some(model) = self.treeview.model() else {
+            return;
+        };
 
+        model.foreach(|model, path, iter| {
+            let is_overlay = Sidebar::folder_path(model, iter).is_none()
+                && model.value(iter, NAME_COLUMN as i32).get::<String>().as_deref() == Ok(overlay_name);
+            if is_overlay {
+                self.treeview.expand_to_path(path);
+                self.treeview.set_cursor(path, None::<&gtk::TreeViewColumn>, false);
+            }
+            is_overlay
+        });
+    }
+
+    /// Update the status icon of an overlay, `None` when it is not displayed
+    pub fn set_overlay_status(&self, overlay_name: &str, status: Option<&OverlayStatus>) {
+        let (icon_name, tooltip) = Sidebar::status_icon(status);
+        let has_failed = matches!(status, Some(OverlayStatus::Failed(_)));
+        // Only the active overlays are displayed
+        let is_active = status.is_some();
+        self.store.foreach(|model, _, iter| {
+            let is_overlay = Sidebar::folder_path(model, iter).is_none()
+                && model.value(iter, NAME_COLUMN as i32).get::<String>().as_deref() == Ok(overlay_name);
+            if is_overlay {
+                self.store.set(iter, &[
+                    (STATUS_ICON_COLUMN, &icon_name),
+                    (STATUS_TOOLTIP_COLUMN, &tooltip),
+                    (ACTIVE_COLUMN, &is_active),
+                    (FAILED_COLUMN, &has_failed)
+                ]);
+            }
+            is_overlay
+        });
+
+        // The folders of the overlay appear or disappear with it
+        if self.overlay_filter.get() != OverlayFilter::All {
+            self.filter_model.refilter();
+        }
+    }
+
+    fn status_icon(status: Option<&OverlayStatus>) -> (&'static str, String) {
+        match status {
+            None => ("", "Disabled".to_string()),
+            Some(OverlayStatus::Loading) => ("content-loading-symbolic", "Loading".to_string()),
+            Some(OverlayStatus::Running) => ("media-playback-start-symbolic", "Running".to_string()),
+            Some(OverlayStatus::Disconnected) => ("network-offline-symbolic", "Disconnected from the data source".to_string()),
+            Some(OverlayStatus::Failed(reason)) => ("dialog-error-symbolic", format!("Failed: {}", reason)),
+        }
+    }
+
+    fn fill_store(model: &gtk::TreeStore, overlays_list: Vec<LayoutConfig>, created_folders: &BTreeSet<String>) {
+        let folders: BTreeSet<String> = overlays_list
+            .iter()
+            .filter_map(LayoutConfig::folder)
+            .chain(created_folders.iter().cloned())
+            .collect();
+        let mut folder_iters = HashMap::new();
+        for folder in folders.iter() {
+            Sidebar::folder_iter(model, &mut folder_iters, folder);
+        }
+
+        let (icon_name, tooltip) = Sidebar::status_icon(None);
         for entry in overlays_list.iter() {
-            let _ = model.insert_with_values(None, None, &[(0, &entry.name())]); // The iterator returned will be used to handle folders
+            let parent = entry.folder().map(|folder| Sidebar::folder_iter(model, &mut folder_iters, &folder));
+            let modified_time = overlay_modified_time(entry)
+                .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                .map(|duration| duration.as_secs() as i64)
+                .unwrap_or(0);
+            let _ = model.insert_with_values(parent.as_ref(), None, &[
+                (NAME_COLUMN, &entry.name()),
+                (STATUS_ICON_COLUMN, &icon_name),
+                (STATUS_TOOLTIP_COLUMN, &tooltip),
+                (FOLDER_PATH_COLUMN, &""),
+                (URL_COLUMN, &entry.url()),
+                (TAGS_COLUMN, &entry.tags().join(" ")),
+                (MODIFIED_COLUMN, &modified_time),
+                (ACTIVE_COLUMN, &entry.is_active()),
+                (FAILED_COLUMN, &false)
+            ]);
         }
-        model
+    }
+
+    fn folder_iters(model: &gtk::TreeStore) -> HashMap<String, gtk::TreeIter> {
+        let mut folder_iters = HashMap::new();
+        model.foreach(|model, _, iter| {
+            if let Some(folder) = Sidebar::folder_path(model, iter) {
+                folder_iters.insert(folder, *iter);
+            }
+            false
+        });
+        folder_iters
+    }
+
+    /// The row of a folder, created with its parents when missing
+    fn folder_iter(model: &gtk::TreeStore, folder_iters: &mut HashMap<String, gtk::TreeIter>, folder: &str) -> gtk::TreeIter {
+        if let Some(iter) = folder_iters.get(folder) {
+            return *iter;
+        }
+
+        let (parent, name) = match folder.rsplit_once('/') {
+            Some((parent_folder, name)) => (Some(Sidebar::folder_iter(model, folder_iters, parent_folder)), name),
+            None => (None, folder)
+        };
+        let iter = model.insert_with_values(parent.as_ref(), None, &[
+            (NAME_COLUMN, &name),
+            (STATUS_ICON_COLUMN, &"folder-symbolic"),
+            (STATUS_TOOLTIP_COLUMN, &folder),
+            (FOLDER_PATH_COLUMN, &folder)
+        ]);
+        folder_iters.insert(folder.to_string(), iter);
+        iter
+    }
+
+    /// Folders have no toggle, they are enabled from their menu
+    fn append_active_column(tree: &gtk::TreeView, id: i32) -> gtk::CellRendererToggle {
+        let column = gtk::TreeViewColumn::new();
+        let cell = gtk::CellRendererToggle::new();
+
+        gtk::prelude::CellLayoutExt::pack_start(&column, &cell, false);
+        gtk::prelude::TreeViewColumnExt::add_attribute(&column, &cell, "active", id);
+        gtk::prelude::CellLayoutExt::set_cell_data_func(&column, &cell, Some(Box::new(|_, cell, model, iter| {
+            cell.set_visible(Sidebar::folder_path(model, iter).is_none());
+        })));
+
+        tree.append_column(&column);
+        cell
+    }
+
+    fn append_status_column(tree: &gtk::TreeView, id: i32) {
+        let column = gtk::TreeViewColumn::new();
+        let cell = gtk::CellRendererPixbuf::new();
+
+        gtk::prelude::CellLayoutExt::pack_start(&column, &cell, false);
+        gtk::prelude::TreeViewColumnExt::add_attribute(&column, &cell, "icon-name", id);
+
+        tree.append_column(&column);
     }
 
     fn append_treeview_column(tree: &gtk::TreeView, id: i32) {
@@ -123,4 +852,68 @@ impl Sidebar {
 
         tree.append_column(&column);
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(name: &str) -> RowInfo {
+        RowInfo { name: name.to_string(), url: format!("https://{}.example/", name.to_lowercase()), ..Default::default() }
+    }
+
+    fn folder(name: &str) -> RowInfo {
+        RowInfo { is_folder: true, ..row(name) }
+    }
+
+    fn sorted(rows: &[RowInfo], sort_order: SortOrder) -> Vec<String> {
+        let mut rows = rows.to_vec();
+        rows.sort_by(|first, second| Sidebar::compare_rows(first, second, sort_order));
+        rows.into_iter().map(|row| row.name).collect()
+    }
+
+    #[test]
+    fn the_search_looks_in_the_name_the_url_and_the_tags() {
+        let meter = RowInfo { tags: "dps raid".to_string(), ..row("Kagerou") };
+
+        assert!(Sidebar::is_overlay_matching(&meter, "", OverlayFilter::All));
+        assert!(Sidebar::is_overlay_matching(&meter, "kager", OverlayFilter::All));
+        assert!(Sidebar::is_overlay_matching(&meter, "kagerou.example", OverlayFilter::All));
+        assert!(Sidebar::is_overlay_matching(&meter, "raid", OverlayFilter::All));
+        assert!(!Sidebar::is_overlay_matching(&meter, "timers", OverlayFilter::All));
+    }
+
+    #[test]
+    fn the_filter_keeps_the_active_or_failing_overlays() {
+        let disabled = row("Meter");
+        let active = RowInfo { is_active: true, ..row("Timers") };
+        let failing = RowInfo { is_active: true, has_failed: true, ..row("Cactbot") };
+
+        let matching = |filter| [&disabled, &active, &failing]
+            .iter()
+            .filter(|row| Sidebar::is_overlay_matching(row, "", filter))
+            .map(|row| row.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(matching(OverlayFilter::All), vec!["Meter", "Timers", "Cactbot"]);
+        assert_eq!(matching(OverlayFilter::Active), vec!["Timers", "Cactbot"]);
+        assert_eq!(matching(OverlayFilter::Failing), vec!["Cactbot"]);
+
+        // The search applies on top of the filter
+        assert!(!Sidebar::is_overlay_matching(&failing, "timers", OverlayFilter::Active));
+    }
+
+    #[test]
+    fn the_folders_come_first_then_the_overlays_in_the_sort_order() {
+        let rows = [
+            RowInfo { modified_time: 300, ..row("meter") },
+            RowInfo { modified_time: 100, is_active: true, ..row("Timers") },
+            folder("Raids"),
+            RowInfo { modified_time: 200, ..row("Cactbot") },
+            folder("Alliance")
+        ];
+
+        assert_eq!(sorted(&rows, SortOrder::Name), vec!["Alliance", "Raids", "Cactbot", "meter", "Timers"]);
+        assert_eq!(sorted(&rows, SortOrder::LastModified), vec!["Alliance", "Raids", "meter", "Cactbot", "Timers"]);
+        assert_eq!(sorted(&rows, SortOrder::ActiveFirst), vec!["Alliance", "Raids", "Timers", "Cactbot", "meter"]);
+    }
+}